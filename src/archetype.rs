@@ -0,0 +1,465 @@
+use bevy::prelude::*;
+use bevy::asset::{AssetLoader, LoadContext, LoadState};
+use bevy::asset::io::Reader;
+use bevy::asset::AsyncReadExt;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use crate::atlas::AnimationSet;
+use crate::modules::ModuleSlot;
+
+pub const ARCHETYPES_PATH: &str = "archetypes.ron";
+/// Stack size of items without an item definition
+const DEFAULT_STACK_SIZE: u32 = 50;
+
+/// Copy of the archetype file compiled into the binary, used until the asset
+/// loads and whenever it can't be read.
+const BUILTIN_ARCHETYPES: &str = include_str!("../assets/archetypes.ron");
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub enum SpriteDefinition {
+  Still(usize),
+  Animated(AnimationSet),
+}
+
+impl SpriteDefinition {
+  pub fn animations(&self) -> AnimationSet {
+    match self {
+      SpriteDefinition::Still(index) => AnimationSet::still(*index),
+      SpriteDefinition::Animated(animations) => *animations,
+    }
+  }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BotDefinition {
+  pub color: (f32, f32, f32),
+  #[serde(default)]
+  pub sprite: Option<SpriteDefinition>,
+  pub ticks_per_tile: u32,
+  pub work_rate: u32,
+  pub carry_capacity: u32,
+  #[serde(default)]
+  pub energy_capacity: u32,
+  /// Components a bot factory spends to build one
+  #[serde(default)]
+  pub cost: BTreeMap<String, u32>,
+  #[serde(default)]
+  pub build_ticks: u32,
+  #[serde(default = "default_health")]
+  pub health: u32,
+  /// How far the bot sees, in tiles
+  #[serde(default = "default_sight_radius")]
+  pub sight_radius: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ResourceDefinition {
+  pub color: (f32, f32, f32),
+  #[serde(default)]
+  pub sprite: Option<SpriteDefinition>,
+  pub size: u32,
+  pub mining_ticks: u32,
+  pub impassable: bool,
+  /// Multiplies the ticks each mining session takes
+  #[serde(default = "default_hardness")]
+  pub hardness: u32,
+  /// Lowest tool tier a bot needs to mine it, from its modules
+  #[serde(default)]
+  pub tier: u32,
+  /// Most one mining session breaks off, or as much as the bot can carry
+  #[serde(default)]
+  pub yield_per_session: Option<u32>,
+}
+
+fn default_hardness() -> u32 {
+  1
+}
+
+fn default_impassable() -> bool {
+  true
+}
+
+fn default_health() -> u32 {
+  100
+}
+
+fn default_sight_radius() -> u32 {
+  5
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BuildingDefinition {
+  pub color: (f32, f32, f32),
+  #[serde(default)]
+  pub sprite: Option<SpriteDefinition>,
+  #[serde(default = "default_impassable")]
+  pub impassable: bool,
+  /// Capacity of colony storage held by the building, if it is a store
+  #[serde(default)]
+  pub storage: Option<u32>,
+  /// Recipes the building can run, in order of preference
+  #[serde(default)]
+  pub recipes: Vec<String>,
+  /// Capacity of each of the input and output buffers
+  #[serde(default)]
+  pub buffer_capacity: u32,
+  /// Whether the building assembles queued bots from its input buffer
+  #[serde(default)]
+  pub bot_factory: bool,
+  /// How many bots the building adds to the population cap
+  #[serde(default)]
+  pub housing: u32,
+  /// Whether bots can have modules installed here
+  #[serde(default)]
+  pub workshop: bool,
+  /// Research points bots generate by working the building, if it is a lab
+  #[serde(default)]
+  pub research: Option<ResearchDefinition>,
+  /// How the building opens for bots passing through, if it is a door
+  #[serde(default)]
+  pub door: Option<DoorDefinition>,
+  /// Items bots deliver to a blueprint before building it
+  #[serde(default)]
+  pub cost: BTreeMap<String, u32>,
+  /// Ticks of bot work to finish a supplied blueprint
+  #[serde(default)]
+  pub build_ticks: u32,
+  #[serde(default = "default_health")]
+  pub health: u32,
+  /// How the building shoots at hostiles, if it is a turret
+  #[serde(default)]
+  pub turret: Option<TurretDefinition>,
+  /// What the building supplies to or draws from its power network, if it is on one
+  #[serde(default)]
+  pub power: Option<PowerDefinition>,
+  /// How the building recharges bots, if it is a charger
+  #[serde(default)]
+  pub charger: Option<ChargerDefinition>,
+  /// How fast the building carries items along, if it is a conveyor belt
+  #[serde(default)]
+  pub conveyor: Option<ConveyorDefinition>,
+  /// How the building passes items on, if it is an inserter
+  #[serde(default)]
+  pub inserter: Option<InserterDefinition>,
+  /// How far the building lights up the dark, if it is a lamp
+  #[serde(default)]
+  pub light: Option<LightDefinition>,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct LightDefinition {
+  /// Distance the light reaches, in tiles
+  pub radius: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ConveyorDefinition {
+  /// Fixed ticks a stack takes to travel one tile
+  pub ticks_per_tile: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct InserterDefinition {
+  /// Fixed ticks between moves
+  pub ticks: u32,
+  /// Most items moved at once
+  pub amount: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct PowerDefinition {
+  /// Power put into the network, for generators
+  #[serde(default)]
+  pub output: u32,
+  /// Power needed to work at full speed, for consumers
+  #[serde(default)]
+  pub draw: u32,
+  /// Whether `output` follows the sun, none at night
+  #[serde(default)]
+  pub solar: bool,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ChargerDefinition {
+  /// Energy put into a bot's battery per tick
+  pub rate: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct TurretDefinition {
+  /// Furthest a target can be, in tiles
+  pub range: f32,
+  pub damage: u32,
+  /// Ticks between shots
+  pub fire_ticks: u32,
+}
+
+/// Something that keeps putting new resource nodes down around itself
+#[derive(Deserialize, Clone, Debug)]
+pub struct SpawnerDefinition {
+  pub color: (f32, f32, f32),
+  #[serde(default)]
+  pub sprite: Option<SpriteDefinition>,
+  /// Resource archetype it spawns
+  pub resource: String,
+  /// Ticks between spawns
+  pub interval_ticks: u32,
+  /// Furthest from the spawner a node is put, in tiles
+  pub radius: u32,
+  /// Most nodes of its resource allowed within the radius at once
+  pub cap: u32,
+}
+
+/// A hostile drone that hunts bots and buildings
+#[derive(Deserialize, Clone, Debug)]
+pub struct HostileDefinition {
+  pub color: (f32, f32, f32),
+  #[serde(default)]
+  pub sprite: Option<SpriteDefinition>,
+  pub ticks_per_tile: u32,
+  pub health: u32,
+  /// Damage dealt per attack on an adjacent target
+  pub damage: u32,
+  /// Ticks between attacks
+  pub attack_ticks: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct DoorDefinition {
+  /// Ticks a closed door takes to open fully
+  pub open_ticks: u32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ResearchDefinition {
+  /// Ticks of bot work per session
+  pub ticks: u32,
+  /// Points granted when a session completes
+  pub points: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct RecipeDefinition {
+  pub inputs: BTreeMap<String, u32>,
+  pub outputs: BTreeMap<String, u32>,
+  pub ticks: u32,
+}
+
+/// A bot module item and the stat changes it gives once installed
+#[derive(Deserialize, Clone, Debug)]
+pub struct ModuleDefinition {
+  pub slot: ModuleSlot,
+  /// Ticks a workshop interaction takes to install it
+  pub install_ticks: u32,
+  #[serde(default)]
+  pub work_rate: u32,
+  /// Percent added to the bot's movement speed
+  #[serde(default)]
+  pub speed: u32,
+  #[serde(default)]
+  pub carry_capacity: u32,
+  #[serde(default)]
+  pub energy_capacity: u32,
+  /// Damage the bot deals to nearby hostiles per attack
+  #[serde(default)]
+  pub attack: u32,
+  /// Mining sessions a drill lasts before it wears out
+  #[serde(default)]
+  pub durability: Option<u32>,
+  /// Tool tier the module gives for mining harder resources
+  #[serde(default)]
+  pub tier: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ItemDefinition {
+  /// Most of the item one tile holds
+  pub stack_size: u32,
+}
+
+/// Something a tech makes available. Until the tech is researched it is locked.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum Unlock {
+  Building(String),
+  Module(String),
+  Recipe(String),
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TechDefinition {
+  /// Research points needed to complete it
+  pub cost: u32,
+  #[serde(default)]
+  pub prerequisites: Vec<String>,
+  #[serde(default)]
+  pub unlocks: Vec<Unlock>,
+}
+
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct ArchetypeLibrary {
+  pub bots: HashMap<String, BotDefinition>,
+  pub resources: HashMap<String, ResourceDefinition>,
+  #[serde(default)]
+  pub buildings: HashMap<String, BuildingDefinition>,
+  #[serde(default)]
+  pub recipes: HashMap<String, RecipeDefinition>,
+  #[serde(default)]
+  pub modules: HashMap<String, ModuleDefinition>,
+  #[serde(default)]
+  pub techs: HashMap<String, TechDefinition>,
+  #[serde(default)]
+  pub items: HashMap<String, ItemDefinition>,
+  #[serde(default)]
+  pub hostiles: HashMap<String, HostileDefinition>,
+  #[serde(default)]
+  pub spawners: HashMap<String, SpawnerDefinition>,
+}
+
+impl ArchetypeLibrary {
+  pub fn builtin() -> Self {
+    ron::from_str(BUILTIN_ARCHETYPES).expect("built-in archetypes.ron is invalid")
+  }
+}
+
+/// Names the definition an entity was spawned from, so hot-reloads can find it
+#[derive(Component, Clone, Debug)]
+pub struct Archetype(pub String);
+
+#[derive(Resource)]
+pub struct Archetypes {
+  handle: Handle<ArchetypeLibrary>,
+  pub library: ArchetypeLibrary,
+  pub ready: bool,
+}
+
+impl Archetypes {
+  pub fn bot(&self, name: &str) -> Option<&BotDefinition> {
+    self.library.bots.get(name)
+  }
+
+  pub fn resource(&self, name: &str) -> Option<&ResourceDefinition> {
+    self.library.resources.get(name)
+  }
+
+  pub fn building(&self, name: &str) -> Option<&BuildingDefinition> {
+    self.library.buildings.get(name)
+  }
+
+  pub fn hostile(&self, name: &str) -> Option<&HostileDefinition> {
+    self.library.hostiles.get(name)
+  }
+
+  pub fn spawner(&self, name: &str) -> Option<&SpawnerDefinition> {
+    self.library.spawners.get(name)
+  }
+
+  pub fn recipe(&self, name: &str) -> Option<&RecipeDefinition> {
+    self.library.recipes.get(name)
+  }
+
+  pub fn module(&self, name: &str) -> Option<&ModuleDefinition> {
+    self.library.modules.get(name)
+  }
+
+  pub fn tech(&self, name: &str) -> Option<&TechDefinition> {
+    self.library.techs.get(name)
+  }
+
+  pub fn stack_size(&self, item: &str) -> u32 {
+    self.library.items.get(item).map_or(DEFAULT_STACK_SIZE, |definition| definition.stack_size.max(1))
+  }
+}
+
+#[cfg(test)]
+impl Archetypes {
+  /// The built-in library, without an asset server behind it
+  pub fn builtin() -> Self {
+    Self { handle: Handle::default(), library: ArchetypeLibrary::builtin(), ready: true }
+  }
+}
+
+#[derive(Debug)]
+pub enum ArchetypeLoaderError {
+  Io(std::io::Error),
+  Ron(ron::error::SpannedError),
+}
+
+impl fmt::Display for ArchetypeLoaderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ArchetypeLoaderError::Io(error) => write!(f, "could not read archetypes: {}", error),
+      ArchetypeLoaderError::Ron(error) => write!(f, "could not parse archetypes: {}", error),
+    }
+  }
+}
+
+impl std::error::Error for ArchetypeLoaderError {}
+
+#[derive(Default)]
+pub struct ArchetypeLoader;
+
+impl AssetLoader for ArchetypeLoader {
+  type Asset = ArchetypeLibrary;
+  type Settings = ();
+  type Error = ArchetypeLoaderError;
+
+  async fn load<'a>(
+    &'a self,
+    reader: &'a mut Reader<'_>,
+    _settings: &'a (),
+    _load_context: &'a mut LoadContext<'_>,
+  ) -> Result<Self::Asset, Self::Error> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await.map_err(ArchetypeLoaderError::Io)?;
+    ron::de::from_bytes(&bytes).map_err(ArchetypeLoaderError::Ron)
+  }
+
+  fn extensions(&self) -> &[&str] {
+    &["ron"]
+  }
+}
+
+pub fn load_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+  commands.insert_resource(Archetypes {
+    handle: asset_server.load(ARCHETYPES_PATH),
+    library: ArchetypeLibrary::builtin(),
+    ready: false,
+  });
+}
+
+pub fn archetypes_ready(archetypes: Res<Archetypes>) -> bool {
+  archetypes.ready
+}
+
+/// Picks up the archetype file once it has loaded and again every time it is
+/// modified on disk.
+pub fn update_archetypes(
+  asset_server: Res<AssetServer>,
+  mut events: EventReader<AssetEvent<ArchetypeLibrary>>,
+  libraries: Res<Assets<ArchetypeLibrary>>,
+  mut archetypes: ResMut<Archetypes>,
+) {
+  for event in events.read() {
+    let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event else {
+      continue;
+    };
+
+    if *id != archetypes.handle.id() {
+      continue;
+    }
+
+    if let Some(library) = libraries.get(*id) {
+      info!("Loaded archetypes from {}", ARCHETYPES_PATH);
+      archetypes.library = library.clone();
+      archetypes.ready = true;
+    }
+  }
+
+  if !archetypes.ready {
+    if let Some(LoadState::Failed(error)) = asset_server.get_load_state(&archetypes.handle) {
+      warn!("Using built-in archetypes: {}", error);
+      archetypes.ready = true;
+    }
+  }
+}
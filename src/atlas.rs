@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+use bevy::asset::LoadState;
+use serde::Deserialize;
+use crate::grid::{Grid, Position, Terrain, TileSprite};
+use crate::renderable::Renderable;
+use crate::pathfinding::Path;
+use crate::interact::Interaction;
+
+pub const ATLAS_PATH: &str = "sprites/atlas.png";
+pub const ATLAS_CELL_SIZE: u32 = 32;
+pub const ATLAS_COLUMNS: u32 = 16;
+pub const ATLAS_ROWS: u32 = 4;
+
+/// Each terrain owns one atlas row of 16 cells, indexed by a neighbor mask
+/// (N = 1, E = 2, S = 4, W = 8) of adjacent tiles with the same terrain.
+const FLOOR_ROW: usize = 0;
+const PLATING_ROW: usize = 1;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Animation {
+  pub first: usize,
+  pub frames: usize,
+  pub fps: f32,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct AnimationSet {
+  pub idle: Animation,
+  pub walking: Animation,
+  pub mining: Animation,
+}
+
+impl AnimationSet {
+  /// A single frame used for every state
+  pub fn still(index: usize) -> Self {
+    let frame = Animation { first: index, frames: 1, fps: 1.0 };
+    Self { idle: frame, walking: frame, mining: frame }
+  }
+
+  pub fn get(&self, state: AnimationState) -> Animation {
+    match state {
+      AnimationState::Idle => self.idle,
+      AnimationState::Walking => self.walking,
+      AnimationState::Mining => self.mining,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimationState {
+  Idle,
+  Walking,
+  Mining,
+}
+
+#[derive(Component)]
+pub struct AnimationPlayer {
+  state: AnimationState,
+  frame: usize,
+  elapsed: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtlasStatus {
+  Loading,
+  Ready,
+  Missing,
+}
+
+#[derive(Resource)]
+pub struct SpriteAtlas {
+  pub image: Handle<Image>,
+  pub layout: Handle<TextureAtlasLayout>,
+  pub status: AtlasStatus,
+}
+
+impl SpriteAtlas {
+  pub fn is_ready(&self) -> bool {
+    self.status == AtlasStatus::Ready
+  }
+}
+
+pub fn load_sprite_atlas(
+  mut commands: Commands,
+  asset_server: Res<AssetServer>,
+  mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+  let layout = TextureAtlasLayout::from_grid(UVec2::splat(ATLAS_CELL_SIZE), ATLAS_COLUMNS, ATLAS_ROWS, None, None);
+
+  commands.insert_resource(SpriteAtlas {
+    image: asset_server.load(ATLAS_PATH),
+    layout: layouts.add(layout),
+    status: AtlasStatus::Loading,
+  });
+}
+
+pub fn check_sprite_atlas(asset_server: Res<AssetServer>, mut atlas: ResMut<SpriteAtlas>) {
+  if atlas.status != AtlasStatus::Loading {
+    return;
+  }
+
+  match asset_server.get_load_state(&atlas.image) {
+    Some(LoadState::Loaded) => atlas.status = AtlasStatus::Ready,
+    Some(LoadState::Failed(error)) => {
+      warn!("Sprite atlas unavailable, falling back to colored tiles: {}", error);
+      atlas.status = AtlasStatus::Missing;
+    },
+    _ => {},
+  }
+}
+
+/// Swaps the colored squares of renderables over to atlas sprites once the
+/// atlas has loaded. Renderables without animations keep their color.
+pub fn attach_atlas_sprites(
+  mut commands: Commands,
+  atlas: Res<SpriteAtlas>,
+  renderables: Query<(Entity, &Renderable), Without<AnimationPlayer>>,
+  mut sprites: Query<&mut Sprite>,
+) {
+  if !atlas.is_ready() {
+    return;
+  }
+
+  for (entity, renderable) in renderables.iter() {
+    let (Some(sprite_entity), Some(animations)) = (renderable.sprite_entity(), renderable.animations()) else {
+      continue;
+    };
+
+    let Ok(mut sprite) = sprites.get_mut(sprite_entity) else {
+      continue;
+    };
+    sprite.color = Color::WHITE;
+
+    commands.entity(sprite_entity).insert((
+      atlas.image.clone(),
+      TextureAtlas { layout: atlas.layout.clone(), index: animations.idle.first },
+    ));
+    commands.entity(entity).insert(AnimationPlayer {
+      state: AnimationState::Idle,
+      frame: 0,
+      elapsed: 0.0,
+    });
+  }
+}
+
+pub fn animate_sprites(
+  time: Res<Time>,
+  mut players: Query<(&Renderable, &mut AnimationPlayer, Option<&Path>, Option<&Interaction>)>,
+  mut texture_atlases: Query<&mut TextureAtlas>,
+) {
+  for (renderable, mut player, path, interaction) in players.iter_mut() {
+    let (Some(sprite_entity), Some(animations)) = (renderable.sprite_entity(), renderable.animations()) else {
+      continue;
+    };
+
+    let state = if interaction.is_some_and(|interaction| !interaction.completed) {
+      AnimationState::Mining
+    } else if path.is_some_and(|path| !path.path.is_empty()) {
+      AnimationState::Walking
+    } else {
+      AnimationState::Idle
+    };
+
+    if state != player.state {
+      player.state = state;
+      player.frame = 0;
+      player.elapsed = 0.0;
+    }
+
+    let animation = animations.get(state);
+    player.elapsed += time.delta_seconds();
+    let frame_duration = 1.0 / animation.fps;
+    while player.elapsed >= frame_duration {
+      player.elapsed -= frame_duration;
+      player.frame = (player.frame + 1) % animation.frames;
+    }
+
+    if let Ok(mut texture_atlas) = texture_atlases.get_mut(sprite_entity) {
+      let index = animation.first + player.frame;
+      if texture_atlas.index != index {
+        texture_atlas.index = index;
+      }
+    }
+  }
+}
+
+/// Picks the atlas cell for each floor tile from its same-terrain neighbors
+pub fn autotile_terrain(
+  mut commands: Commands,
+  grid: Res<Grid>,
+  atlas: Res<SpriteAtlas>,
+  mut tiles: Query<(Entity, &TileSprite, &mut Sprite), Without<TextureAtlas>>,
+) {
+  if !atlas.is_ready() {
+    return;
+  }
+
+  for (entity, tile_sprite, mut sprite) in tiles.iter_mut() {
+    let Some(terrain) = grid.terrain_at(&tile_sprite.position) else {
+      continue;
+    };
+
+    let row = match terrain {
+      Terrain::Floor => FLOOR_ROW,
+      Terrain::Plating => PLATING_ROW,
+    };
+
+    let index = row * ATLAS_COLUMNS as usize + neighbor_mask(&grid, &tile_sprite.position, terrain);
+    sprite.color = Color::WHITE;
+    commands.entity(entity).insert((
+      atlas.image.clone(),
+      TextureAtlas { layout: atlas.layout.clone(), index },
+    ));
+  }
+}
+
+fn neighbor_mask(grid: &Grid, position: &Position, terrain: Terrain) -> usize {
+  let neighbors = [
+    (position.x, position.y + 1),
+    (position.x + 1, position.y),
+    (position.x, position.y.wrapping_sub(1)),
+    (position.x.wrapping_sub(1), position.y),
+  ];
+
+  neighbors.iter().enumerate().fold(0, |mask, (bit, &(nx, ny))| {
+    if nx < grid.width && ny < grid.height && grid.terrain_at(&Position::new(nx, ny)) == Some(terrain) {
+      mask | (1 << bit)
+    } else {
+      mask
+    }
+  })
+}
@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use crate::grid::Position;
+use crate::entities::bot::Bot;
+use crate::interact::Interaction;
+use crate::simulation::SimulationClock;
+use crate::inventory::Storage;
+use crate::items::Item;
+use crate::zones::{self, StockpileZone};
+use crate::archetype::{Archetype, Archetypes};
+use crate::entities::building::Building;
+
+/// Fixed ticks in one in-game minute at 1x speed
+pub const TICKS_PER_MINUTE: u64 = 600;
+/// How many minutes of mining history the HUD graph keeps
+pub const MINING_HISTORY_MINUTES: usize = 10;
+/// A bot holding a job without moving or working for this long is stalled
+const STALL_TICKS: u64 = 100;
+/// Alerts disappear after this many ticks unless raised again
+const ALERT_LIFETIME_TICKS: u64 = 300;
+
+/// Items held across storage buildings and stockpile zones, keyed by item name
+#[derive(Resource, Default)]
+pub struct Stockpile {
+  pub items: BTreeMap<String, u32>,
+}
+
+/// Bots alive against the cap provided by the colony's buildings
+#[derive(Resource, Default)]
+pub struct Population {
+  pub bots: u32,
+  pub cap: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct ColonyStats {
+  /// Scrap mined per in-game minute, oldest first, current minute last
+  pub mined_per_minute: VecDeque<u32>,
+  current_minute: u64,
+}
+
+impl ColonyStats {
+  pub fn record_mined(&mut self, amount: u32, tick: u64) {
+    self.roll_to(tick);
+    if let Some(current) = self.mined_per_minute.back_mut() {
+      *current += amount;
+    }
+  }
+
+  fn roll_to(&mut self, tick: u64) {
+    let minute = tick / TICKS_PER_MINUTE;
+    if self.mined_per_minute.is_empty() {
+      self.mined_per_minute.push_back(0);
+      self.current_minute = minute;
+    }
+
+    while self.current_minute < minute {
+      self.mined_per_minute.push_back(0);
+      self.current_minute += 1;
+      if self.mined_per_minute.len() > MINING_HISTORY_MINUTES {
+        self.mined_per_minute.pop_front();
+      }
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AlertKind {
+  UnreachableJob(Entity),
+  StalledBot(Entity),
+  BlockedFactory(Entity),
+  Destroyed(Entity),
+  /// A resource node whose yield has nowhere to go
+  NoRoomToDrop(Entity),
+  HostileWave,
+  /// Keyed by the tick the event fired, so a later event doesn't replace it
+  WorldEvent(u64),
+}
+
+#[derive(Clone, Debug)]
+pub struct Alert {
+  pub message: String,
+  pub raised_at: u64,
+}
+
+#[derive(Resource, Default)]
+pub struct Alerts {
+  active: HashMap<AlertKind, Alert>,
+}
+
+impl Alerts {
+  /// Raises an alert, or refreshes it if the same kind is already active
+  pub fn raise(&mut self, kind: AlertKind, message: String, tick: u64) {
+    self.active.insert(kind, Alert { message, raised_at: tick });
+  }
+
+  pub fn clear(&mut self, kind: &AlertKind) {
+    self.active.remove(kind);
+  }
+
+  /// Active alerts, newest first
+  pub fn iter(&self) -> impl Iterator<Item = &Alert> {
+    let mut alerts: Vec<&Alert> = self.active.values().collect();
+    alerts.sort_by_key(|alert| std::cmp::Reverse(alert.raised_at));
+    alerts.into_iter()
+  }
+}
+
+pub fn tally_stockpile(
+  storages: Query<&Storage>,
+  loose: Query<(&Item, &Position)>,
+  zones: Query<&StockpileZone>,
+  mut stockpile: ResMut<Stockpile>,
+) {
+  let mut items = BTreeMap::new();
+  for storage in storages.iter() {
+    for (item, count) in &storage.0.items {
+      *items.entry(item.clone()).or_insert(0) += count;
+    }
+  }
+  for (item, _) in loose.iter().filter(|(item, position)| zones::is_stored(position, &item.name, &zones)) {
+    *items.entry(item.name.clone()).or_insert(0) += item.count;
+  }
+
+  if stockpile.items != items {
+    stockpile.items = items;
+  }
+}
+
+pub fn tally_population(
+  archetypes: Res<Archetypes>,
+  bots: Query<(), With<Bot>>,
+  buildings: Query<&Archetype, With<Building>>,
+  mut population: ResMut<Population>,
+) {
+  let bots = bots.iter().count() as u32;
+  let cap = buildings.iter()
+    .filter_map(|archetype| archetypes.building(&archetype.0))
+    .map(|definition| definition.housing)
+    .sum();
+
+  if population.bots != bots || population.cap != cap {
+    *population = Population { bots, cap };
+  }
+}
+
+pub fn advance_stats(clock: Res<SimulationClock>, mut stats: ResMut<ColonyStats>) {
+  stats.roll_to(clock.tick);
+}
+
+pub fn expire_alerts(clock: Res<SimulationClock>, mut alerts: ResMut<Alerts>) {
+  alerts.active.retain(|_, alert| clock.tick.saturating_sub(alert.raised_at) < ALERT_LIFETIME_TICKS);
+}
+
+/// Raises an alert for bots that hold a job but have neither moved nor worked
+/// for a while.
+pub fn detect_stalled_bots(
+  clock: Res<SimulationClock>,
+  mut alerts: ResMut<Alerts>,
+  mut last_progress: Local<HashMap<Entity, (Position, u64)>>,
+  bots: Query<(Entity, &Bot, &Position, Option<&Interaction>)>,
+) {
+  let mut seen = Vec::new();
+
+  for (bot_entity, bot, position, interaction) in bots.iter() {
+    seen.push(bot_entity);
+    let kind = AlertKind::StalledBot(bot_entity);
+
+    if bot.job.is_none() || interaction.is_some() {
+      last_progress.insert(bot_entity, (*position, clock.tick));
+      alerts.clear(&kind);
+      continue;
+    }
+
+    let progress = last_progress.entry(bot_entity).or_insert((*position, clock.tick));
+    if progress.0 != *position {
+      *progress = (*position, clock.tick);
+      alerts.clear(&kind);
+    } else if clock.tick - progress.1 >= STALL_TICKS {
+      alerts.raise(kind, format!("Bot {} is stalled", bot_entity.index()), clock.tick);
+    }
+  }
+
+  last_progress.retain(|entity, _| seen.contains(entity));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn mining_is_recorded_in_the_current_minute() {
+    let mut stats = ColonyStats::default();
+    stats.record_mined(3, 0);
+    stats.record_mined(2, TICKS_PER_MINUTE - 1);
+    stats.record_mined(4, TICKS_PER_MINUTE);
+    assert_eq!(stats.mined_per_minute, [5, 4]);
+  }
+
+  #[test]
+  fn idle_minutes_roll_in_as_zeros_and_old_ones_drop_off() {
+    let mut stats = ColonyStats::default();
+    stats.record_mined(7, 0);
+    stats.roll_to(3 * TICKS_PER_MINUTE);
+    assert_eq!(stats.mined_per_minute, [7, 0, 0, 0]);
+
+    stats.roll_to(20 * TICKS_PER_MINUTE);
+    assert_eq!(stats.mined_per_minute.len(), MINING_HISTORY_MINUTES);
+    assert!(stats.mined_per_minute.iter().all(|mined| *mined == 0));
+  }
+}
@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use std::collections::{HashMap, HashSet};
+use crate::grid::{Grid, Position};
+use crate::archetype::{Archetypes, ConveyorDefinition, InserterDefinition};
+use crate::simulation::SimulationClock;
+use crate::research::Research;
+use crate::power::PowerNode;
+use crate::items::{self, Item};
+use crate::inventory::{InputBuffer, OutputBuffer, Storage};
+use crate::entities::building::{self, Building, Blueprint, Fabricator, BotFactory, Locked};
+
+/// Which way a belt carries items, or an inserter passes them
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Heading {
+  North,
+  #[default]
+  East,
+  South,
+  West,
+}
+
+impl Heading {
+  pub fn clockwise(self) -> Self {
+    match self {
+      Heading::North => Heading::East,
+      Heading::East => Heading::South,
+      Heading::South => Heading::West,
+      Heading::West => Heading::North,
+    }
+  }
+
+  fn reverse(self) -> Self {
+    self.clockwise().clockwise()
+  }
+
+  pub fn label(&self) -> &'static str {
+    match self {
+      Heading::North => "north",
+      Heading::East => "east",
+      Heading::South => "south",
+      Heading::West => "west",
+    }
+  }
+
+  pub fn vector(self) -> Vec2 {
+    match self {
+      Heading::North => Vec2::Y,
+      Heading::East => Vec2::X,
+      Heading::South => Vec2::NEG_Y,
+      Heading::West => Vec2::NEG_X,
+    }
+  }
+
+  /// The tile one step this way, if it is on the grid
+  pub fn ahead(self, position: &Position, grid: &Grid) -> Option<Position> {
+    let (x, y) = match self {
+      Heading::North => (position.x, position.y + 1),
+      Heading::East => (position.x + 1, position.y),
+      Heading::South => (position.x, position.y.checked_sub(1)?),
+      Heading::West => (position.x.checked_sub(1)?, position.y),
+    };
+    (x < grid.width && y < grid.height).then(|| Position::new(x, y))
+  }
+
+  pub fn behind(self, position: &Position, grid: &Grid) -> Option<Position> {
+    self.reverse().ahead(position, grid)
+  }
+}
+
+/// A belt tile that carries the loose stack on it along its heading
+#[derive(Component)]
+pub struct Conveyor(pub ConveyorDefinition);
+
+/// Picks items up from the tile behind it and puts them down on, or into the
+/// building on, the tile ahead
+#[derive(Component)]
+pub struct Inserter(pub InserterDefinition);
+
+type LooseItem = (With<Item>, Without<Conveyor>);
+
+/// Steps every stack on a belt onto the tile ahead when the belt's interval
+/// comes round and that tile is a belt with nothing on it. Moves are decided
+/// from where stacks stood at the start of the tick, so queues of stacks move
+/// up together and merging belts always resolve in the same order.
+pub fn run_conveyors(
+  clock: Res<SimulationClock>,
+  grid: Res<Grid>,
+  belts: Query<(&Position, &Conveyor, &Heading)>,
+  mut items: Query<(Entity, &mut Position), LooseItem>,
+) {
+  let belt_tiles: HashSet<Position> = belts.iter().map(|(position, ..)| *position).collect();
+  let mut occupied: HashMap<Position, Entity> = items.iter().map(|(entity, position)| (*position, entity)).collect();
+
+  let mut moves: Vec<(Position, Position, Entity)> = belts.iter()
+    .filter(|(_, conveyor, _)| clock.tick.is_multiple_of(conveyor.0.ticks_per_tile.max(1) as u64))
+    .filter_map(|(position, _, heading)| {
+      let stack = occupied.get(position)?;
+      let next = heading.ahead(position, &grid).filter(|next| belt_tiles.contains(next))?;
+      Some((*position, next, *stack))
+    })
+    .collect();
+  moves.sort_by_key(|(from, ..)| (from.y, from.x));
+
+  // A stack can only move once the one ahead of it has, so keep sweeping until nothing moves
+  let mut moved = true;
+  while moved {
+    moved = false;
+    moves.retain(|(from, to, stack)| {
+      if occupied.contains_key(to) {
+        return true;
+      }
+      if let Ok((_, mut position)) = items.get_mut(*stack) {
+        *position = *to;
+      }
+      occupied.remove(from);
+      occupied.insert(*to, *stack);
+      moved = true;
+      false
+    });
+  }
+}
+
+type InserterParts = (&'static Position, &'static Inserter, &'static Heading, Option<&'static PowerNode>);
+/// Buildings an inserter can reach into, which belts underneath don't hide
+type Reachable = (Or<(With<Building>, With<Blueprint>)>, Without<Conveyor>);
+type Consumer = (&'static mut InputBuffer, Option<&'static Fabricator>, Option<&'static BotFactory>, Option<&'static Blueprint>);
+
+/// What inserters take from and give to
+#[derive(SystemParam)]
+pub struct InserterTargets<'w, 's> {
+  buildings: Query<'w, 's, (Entity, &'static Position), Reachable>,
+  items: Query<'w, 's, (Entity, &'static Position, &'static mut Item)>,
+  outputs: Query<'w, 's, &'static mut OutputBuffer>,
+  inputs: Query<'w, 's, Consumer, Without<Locked>>,
+  storages: Query<'w, 's, &'static mut Storage>,
+}
+
+/// Moves up to an inserter's `amount` of one item per interval from the tile
+/// behind it to the tile ahead. It takes from a building's output buffer or a
+/// loose stack, and gives to a building's input buffer if the building needs
+/// the item, to storage, or onto the floor or a belt. Stops without power.
+pub fn run_inserters(
+  mut commands: Commands,
+  clock: Res<SimulationClock>,
+  grid: Res<Grid>,
+  archetypes: Res<Archetypes>,
+  research: Res<Research>,
+  inserters: Query<InserterParts, Without<Locked>>,
+  mut targets: InserterTargets,
+) {
+  let mut spawned: HashSet<Position> = HashSet::new();
+
+  for (position, inserter, heading, power) in inserters.iter() {
+    if !clock.tick.is_multiple_of(inserter.0.ticks.max(1) as u64) || power.is_some_and(|power| power.rate() <= 0.0) {
+      continue;
+    }
+    let (Some(from), Some(to)) = (heading.behind(position, &grid), heading.ahead(position, &grid)) else {
+      continue;
+    };
+
+    let building_at = |tile: Position| targets.buildings.iter().find(|(_, position)| **position == tile).map(|(entity, _)| entity);
+    let source = building_at(from);
+    let destination = building_at(to);
+
+    let offer = match source {
+      Some(building) => targets.outputs.get(building).ok().and_then(|output| {
+        output.0.items.iter().find(|(_, count)| **count > 0).map(|(name, count)| (name.clone(), *count))
+      }),
+      None => targets.items.iter().find(|(_, position, _)| **position == from).map(|(_, _, stack)| (stack.name.clone(), stack.count)),
+    };
+    let Some((name, available)) = offer else {
+      continue;
+    };
+
+    let room = match destination {
+      Some(building) => {
+        if let Ok((input, fabricator, factory, blueprint)) = targets.inputs.get(building) {
+          let requirements = building::input_requirements(fabricator, factory, blueprint, &archetypes, &research);
+          building::wanted(&requirements, &name, &input.0)
+        } else if let Ok(storage) = targets.storages.get(building) {
+          storage.0.free_space()
+        } else {
+          0
+        }
+      },
+      None if spawned.contains(&to) => 0,
+      None => {
+        let stack = targets.items.iter().find(|(_, position, _)| **position == to).map(|(_, _, stack)| stack);
+        items::room_on_tile(&name, archetypes.stack_size(&name), stack)
+      },
+    };
+
+    let amount = available.min(inserter.0.amount).min(room);
+    if amount == 0 {
+      continue;
+    }
+
+    match source {
+      Some(building) => {
+        if let Ok(mut output) = targets.outputs.get_mut(building) {
+          output.0.remove(&name, amount);
+        }
+      },
+      None => {
+        if let Some((entity, _, mut stack)) = targets.items.iter_mut().find(|(_, position, _)| **position == from) {
+          stack.count -= amount;
+          if stack.count == 0 {
+            commands.entity(entity).despawn();
+          }
+        }
+      },
+    }
+
+    match destination {
+      Some(building) => {
+        if let Ok((mut input, ..)) = targets.inputs.get_mut(building) {
+          input.0.add(&name, amount);
+        } else if let Ok(mut storage) = targets.storages.get_mut(building) {
+          storage.0.add(&name, amount);
+        }
+      },
+      None => match targets.items.iter_mut().find(|(_, position, _)| **position == to) {
+        Some((_, _, mut stack)) => stack.count += amount,
+        None => {
+          items::spawn_item(&mut commands, &archetypes, &name, amount, to);
+          spawned.insert(to);
+        },
+      },
+    }
+  }
+}
+
+/// Arrows showing which way belts, inserters and their blueprints point
+pub fn draw_headings(grid: Res<Grid>, headings: Query<(&Position, &Heading)>, mut gizmos: Gizmos) {
+  let color = Color::srgba(0.9, 0.9, 0.6, 0.7);
+  for (position, heading) in headings.iter() {
+    let center = grid.world_position(position);
+    let offset = heading.vector() * grid.tile_size * 0.3;
+    gizmos.arrow_2d(center - offset, center + offset, color);
+  }
+}
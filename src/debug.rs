@@ -0,0 +1,201 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use crate::grid::{Grid, Position, Impassable};
+use crate::reservation::{ReservationSystem, ReservationKey};
+use crate::pathfinding::Path;
+use crate::interact::Interaction;
+use crate::entities::bot::Bot;
+use crate::modules::Modules;
+use crate::health::{Health, Broken};
+use crate::energy::Energy;
+
+/// Which debug overlays are drawn. Toggled with F1-F5.
+#[derive(Resource, Default)]
+pub struct DebugOverlays {
+  pub paths: bool,
+  pub reservations: bool,
+  pub impassable: bool,
+  pub regions: bool,
+  pub panel: bool,
+}
+
+#[derive(Component)]
+pub struct DebugPanel;
+
+/// A stable, distinct color per entity so overlays of the same bot match
+pub fn entity_color(entity: Entity) -> Color {
+  Color::hsl((entity.index() as f32 * 137.5) % 360.0, 0.8, 0.6)
+}
+
+pub fn toggle_debug_overlays(keys: Res<ButtonInput<KeyCode>>, mut overlays: ResMut<DebugOverlays>) {
+  if keys.just_pressed(KeyCode::F1) {
+    overlays.paths = !overlays.paths;
+  }
+  if keys.just_pressed(KeyCode::F2) {
+    overlays.reservations = !overlays.reservations;
+  }
+  if keys.just_pressed(KeyCode::F3) {
+    overlays.impassable = !overlays.impassable;
+  }
+  if keys.just_pressed(KeyCode::F4) {
+    overlays.regions = !overlays.regions;
+  }
+  if keys.just_pressed(KeyCode::F5) {
+    overlays.panel = !overlays.panel;
+  }
+}
+
+pub fn draw_path_overlay(
+  overlays: Res<DebugOverlays>,
+  grid: Res<Grid>,
+  paths: Query<(Entity, &Path, &Position)>,
+  mut gizmos: Gizmos,
+) {
+  if !overlays.paths {
+    return;
+  }
+
+  for (entity, path, position) in paths.iter() {
+    let color = entity_color(entity);
+    let points = std::iter::once(position)
+      .chain(path.path.iter())
+      .map(|position| grid.world_position(position));
+    gizmos.linestrip_2d(points, color);
+    gizmos.circle_2d(grid.world_position(&path.target), grid.tile_size * 0.2, color);
+  }
+}
+
+pub fn draw_reservation_overlay(
+  overlays: Res<DebugOverlays>,
+  grid: Res<Grid>,
+  reservations: Res<ReservationSystem>,
+  positions: Query<&Position>,
+  mut gizmos: Gizmos,
+) {
+  if !overlays.reservations {
+    return;
+  }
+
+  let size = Vec2::splat(grid.tile_size * 0.9);
+  for (key, bot) in reservations.iter() {
+    let position = match key {
+      ReservationKey::Tile(position) => Some(*position),
+      ReservationKey::Entity(entity) | ReservationKey::Input(entity) | ReservationKey::Output(entity) => {
+        positions.get(*entity).ok().copied()
+      },
+    };
+
+    if let Some(position) = position {
+      gizmos.rect_2d(grid.world_position(&position), 0.0, size, entity_color(bot));
+    }
+  }
+}
+
+pub fn draw_impassable_overlay(
+  overlays: Res<DebugOverlays>,
+  grid: Res<Grid>,
+  impassable: Query<&Position, With<Impassable>>,
+  mut gizmos: Gizmos,
+) {
+  if !overlays.impassable {
+    return;
+  }
+
+  let color = Color::srgba(1.0, 0.2, 0.2, 0.8);
+  let half = grid.tile_size * 0.5;
+  for position in impassable.iter() {
+    let center = grid.world_position(position);
+    gizmos.rect_2d(center, 0.0, Vec2::splat(grid.tile_size), color);
+    gizmos.line_2d(center + Vec2::new(-half, -half), center + Vec2::new(half, half), color);
+    gizmos.line_2d(center + Vec2::new(-half, half), center + Vec2::new(half, -half), color);
+  }
+}
+
+pub fn draw_region_overlay(
+  overlays: Res<DebugOverlays>,
+  grid: Res<Grid>,
+  impassable: Query<Entity, With<Impassable>>,
+  mut gizmos: Gizmos,
+) {
+  if !overlays.regions {
+    return;
+  }
+
+  let impassable_set: HashSet<Entity> = impassable.iter().collect();
+  let regions = grid.connected_regions(&impassable_set);
+
+  for tile in grid.tiles.iter().flatten() {
+    if let Some(region) = regions[tile.position.index()] {
+      let color = Color::hsla((region as f32 * 97.0) % 360.0, 0.7, 0.5, 0.6);
+      gizmos.circle_2d(grid.world_position(&tile.position), grid.tile_size * 0.15, color);
+    }
+  }
+}
+
+pub fn spawn_debug_panel(mut commands: Commands) {
+  commands.spawn((
+    TextBundle::from_section("", TextStyle {
+      font_size: 12.0,
+      color: Color::srgb(0.8, 0.9, 0.8),
+      ..default()
+    }).with_style(Style {
+      position_type: PositionType::Absolute,
+      bottom: Val::Px(8.0),
+      left: Val::Px(8.0),
+      ..default()
+    }),
+    DebugPanel,
+  ));
+}
+
+type BotDetails = (
+  Entity,
+  &'static Bot,
+  &'static Position,
+  &'static Modules,
+  &'static Energy,
+  &'static Health,
+  Option<&'static Path>,
+  Option<&'static Interaction>,
+  Has<Broken>,
+);
+
+pub fn update_debug_panel(
+  overlays: Res<DebugOverlays>,
+  reservations: Res<ReservationSystem>,
+  bots: Query<BotDetails>,
+  mut panels: Query<(&mut Text, &mut Visibility), With<DebugPanel>>,
+) {
+  for (mut text, mut visibility) in panels.iter_mut() {
+    if !overlays.panel {
+      *visibility = Visibility::Hidden;
+      continue;
+    }
+    *visibility = Visibility::Inherited;
+
+    let mut lines = vec![format!("Reservations: {}", reservations.count())];
+    for (entity, bot, position, modules, energy, health, path, interaction, broken) in bots.iter() {
+      let job = match &bot.job {
+        _ if broken => "broken".to_string(),
+        Some(job) => job.label(),
+        None => "idle".to_string(),
+      };
+      let path = path.map_or("-".to_string(), |path| format!("{} steps", path.path.len()));
+      let interaction = interaction.map_or("-".to_string(), |interaction| {
+        format!("{}/{}", interaction.ticks_completed, interaction.ticks_to_complete)
+      });
+      let modules = if modules.installed.is_empty() {
+        "-".to_string()
+      } else {
+        modules.installed.values().cloned().collect::<Vec<_>>().join(", ")
+      };
+
+      lines.push(format!(
+        "Bot {} ({}, {})  job: {}  path: {}  work: {}  energy: {}/{}  health: {}/{}  modules: {}",
+        entity.index(), position.x, position.y, job, path, interaction, energy.charge, energy.capacity, health.current, health.max, modules
+      ));
+    }
+
+    text.sections[0].value = lines.join("\n");
+  }
+}
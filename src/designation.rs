@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use std::collections::HashSet;
+use crate::grid::{Grid, Impassable, Position, Cursor};
+use crate::entities::scrap::Scrap;
+use crate::entities::building::{Blueprint, Building};
+use crate::archetype::{Archetypes, Unlock};
+use crate::research::Research;
+use crate::inventory::InputBuffer;
+use crate::spawn::spawn_blueprint;
+use crate::items::{Item, Floor};
+use crate::conveyors::Heading;
+use crate::groups::BotGroups;
+
+/// Ticks of bot work it takes to tear a building down
+pub const DECONSTRUCT_TICKS: u32 = 60;
+
+/// A player order on a scrap node or building
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Designation {
+  Mine,
+  Deconstruct,
+}
+
+/// Loose items bots must leave alone
+#[derive(Component)]
+pub struct Forbidden;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DesignationMode {
+  #[default]
+  Mine,
+  Forbid,
+  Deconstruct,
+  Cancel,
+  Wall,
+  Door,
+  Conduit,
+  Generator,
+  Charger,
+  Conveyor,
+  Inserter,
+  Lamp,
+  SolarPanel,
+  GroupArea,
+}
+
+impl DesignationMode {
+  pub fn label(&self) -> &'static str {
+    match self {
+      DesignationMode::Mine => "mine",
+      DesignationMode::Forbid => "forbid",
+      DesignationMode::Deconstruct => "deconstruct",
+      DesignationMode::Cancel => "cancel",
+      DesignationMode::Wall => "build wall",
+      DesignationMode::Door => "build door",
+      DesignationMode::Conduit => "build conduit",
+      DesignationMode::Generator => "build generator",
+      DesignationMode::Charger => "build charger",
+      DesignationMode::Conveyor => "build conveyor",
+      DesignationMode::Inserter => "build inserter",
+      DesignationMode::Lamp => "build lamp",
+      DesignationMode::SolarPanel => "build solar panel",
+      DesignationMode::GroupArea => "group area",
+    }
+  }
+
+  /// The building archetype the tool lays blueprints for, if it is a build tool
+  pub fn building(&self) -> Option<&'static str> {
+    match self {
+      DesignationMode::Wall => Some("wall"),
+      DesignationMode::Door => Some("door"),
+      DesignationMode::Conduit => Some("conduit"),
+      DesignationMode::Generator => Some("generator"),
+      DesignationMode::Charger => Some("charger"),
+      DesignationMode::Conveyor => Some("conveyor"),
+      DesignationMode::Inserter => Some("inserter"),
+      DesignationMode::Lamp => Some("lamp"),
+      DesignationMode::SolarPanel => Some("solar_panel"),
+      _ => None,
+    }
+  }
+
+  fn color(&self) -> Color {
+    match self {
+      DesignationMode::Mine => Color::srgb(1.0, 0.9, 0.2),
+      DesignationMode::Forbid => Color::srgb(1.0, 0.3, 0.3),
+      DesignationMode::Deconstruct => Color::srgb(1.0, 0.5, 0.1),
+      DesignationMode::Cancel => Color::srgb(0.7, 0.7, 0.7),
+      DesignationMode::Wall | DesignationMode::Door | DesignationMode::Conduit | DesignationMode::Generator
+        | DesignationMode::Charger | DesignationMode::Conveyor | DesignationMode::Inserter
+        | DesignationMode::Lamp | DesignationMode::SolarPanel => Color::srgb(0.4, 0.7, 1.0),
+      DesignationMode::GroupArea => Color::srgb(0.3, 0.8, 1.0),
+    }
+  }
+}
+
+#[derive(Resource, Default)]
+pub struct DesignationTool {
+  pub mode: DesignationMode,
+  /// Bots only mine scrap designated for mining
+  pub require_designation: bool,
+  /// The only resource type bots mine, or any when `None`
+  pub mining_target: Option<String>,
+  /// Which way new belts and inserters face
+  pub heading: Heading,
+  drag_start: Option<Position>,
+}
+
+#[derive(Component)]
+pub struct ToolIndicator;
+
+fn in_rect(position: &Position, a: Position, b: Position) -> bool {
+  (a.x.min(b.x)..=a.x.max(b.x)).contains(&position.x) && (a.y.min(b.y)..=a.y.max(b.y)).contains(&position.y)
+}
+
+/// M, N, X and C pick the mine, forbid, deconstruct and cancel tools, W, D,
+/// U, Y, H, V, I, J and O the wall, door, conduit, generator, charger,
+/// conveyor, inserter, lamp and solar panel build tools, and Z the selected
+/// bot group's work area. Q turns new belts and inserters clockwise. G toggles
+/// whether mining needs a designation, and R cycles which resource type bots mine.
+pub fn select_designation_tool(
+  keys: Res<ButtonInput<KeyCode>>,
+  archetypes: Res<Archetypes>,
+  mut tool: ResMut<DesignationTool>,
+) {
+  let mode = if keys.just_pressed(KeyCode::KeyM) {
+    Some(DesignationMode::Mine)
+  } else if keys.just_pressed(KeyCode::KeyN) {
+    Some(DesignationMode::Forbid)
+  } else if keys.just_pressed(KeyCode::KeyX) {
+    Some(DesignationMode::Deconstruct)
+  } else if keys.just_pressed(KeyCode::KeyC) {
+    Some(DesignationMode::Cancel)
+  } else if keys.just_pressed(KeyCode::KeyW) {
+    Some(DesignationMode::Wall)
+  } else if keys.just_pressed(KeyCode::KeyD) {
+    Some(DesignationMode::Door)
+  } else if keys.just_pressed(KeyCode::KeyU) {
+    Some(DesignationMode::Conduit)
+  } else if keys.just_pressed(KeyCode::KeyY) {
+    Some(DesignationMode::Generator)
+  } else if keys.just_pressed(KeyCode::KeyH) {
+    Some(DesignationMode::Charger)
+  } else if keys.just_pressed(KeyCode::KeyV) {
+    Some(DesignationMode::Conveyor)
+  } else if keys.just_pressed(KeyCode::KeyI) {
+    Some(DesignationMode::Inserter)
+  } else if keys.just_pressed(KeyCode::KeyJ) {
+    Some(DesignationMode::Lamp)
+  } else if keys.just_pressed(KeyCode::KeyO) {
+    Some(DesignationMode::SolarPanel)
+  } else if keys.just_pressed(KeyCode::KeyZ) {
+    Some(DesignationMode::GroupArea)
+  } else {
+    None
+  };
+
+  if let Some(mode) = mode {
+    tool.mode = mode;
+  }
+  if keys.just_pressed(KeyCode::KeyQ) {
+    tool.heading = tool.heading.clockwise();
+  }
+  if keys.just_pressed(KeyCode::KeyG) {
+    tool.require_designation = !tool.require_designation;
+  }
+  if keys.just_pressed(KeyCode::KeyR) {
+    let mut resources: Vec<&String> = archetypes.library.resources.keys().collect();
+    resources.sort();
+    // Cycles any -> each resource in turn -> any
+    tool.mining_target = match &tool.mining_target {
+      None => resources.first().map(|name| name.to_string()),
+      Some(current) => resources.iter()
+        .skip_while(|name| **name != current)
+        .nth(1)
+        .map(|name| name.to_string()),
+    };
+  }
+}
+
+/// Everything the designation tools can be applied to
+#[derive(SystemParam)]
+pub struct DesignationTargets<'w, 's> {
+  archetypes: Res<'w, Archetypes>,
+  research: Res<'w, Research>,
+  floor: Floor<'w, 's>,
+  scrap: Query<'w, 's, (Entity, &'static Position), With<Scrap>>,
+  buildings: Query<'w, 's, (Entity, &'static Position), With<Building>>,
+  items: Query<'w, 's, (Entity, &'static Position), With<Item>>,
+  blueprints: Query<'w, 's, (Entity, &'static Position, &'static InputBuffer), With<Blueprint>>,
+  impassable: Query<'w, 's, Entity, With<Impassable>>,
+}
+
+/// Drag with the left mouse button to apply the current tool to a rectangle.
+/// Shift-drags belong to the zone tool.
+pub fn designate_area(
+  mut commands: Commands,
+  mut tool: ResMut<DesignationTool>,
+  mut groups: ResMut<BotGroups>,
+  mouse: Res<ButtonInput<MouseButton>>,
+  keys: Res<ButtonInput<KeyCode>>,
+  cursor: Cursor,
+  mut targets: DesignationTargets,
+) {
+  // Panels and buttons take the clicks over them, and a drag let go over one is dropped
+  if cursor.over_ui() {
+    if mouse.just_released(MouseButton::Left) {
+      tool.drag_start = None;
+    }
+    return;
+  }
+  let Some(tile) = cursor.tile() else {
+    return;
+  };
+
+  let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+  if !shift && mouse.just_pressed(MouseButton::Left) {
+    tool.drag_start = Some(tile);
+  }
+  if !mouse.just_released(MouseButton::Left) {
+    return;
+  }
+  let Some(start) = tool.drag_start.take() else {
+    return;
+  };
+
+  let inside = |position: &Position| in_rect(position, start, tile);
+  match tool.mode {
+    DesignationMode::Mine => {
+      for (entity, _) in targets.scrap.iter().filter(|(_, position)| inside(position)) {
+        commands.entity(entity).insert(Designation::Mine);
+      }
+    },
+    DesignationMode::Forbid => {
+      for (entity, _) in targets.items.iter().filter(|(_, position)| inside(position)) {
+        commands.entity(entity).insert(Forbidden);
+      }
+    },
+    DesignationMode::Deconstruct => {
+      for (entity, _) in targets.buildings.iter().filter(|(_, position)| inside(position)) {
+        commands.entity(entity).insert(Designation::Deconstruct);
+      }
+    },
+    DesignationMode::GroupArea => {
+      if let Some(settings) = groups.selected_mut() {
+        settings.area = Some((
+          Position::new(start.x.min(tile.x), start.y.min(tile.y)),
+          Position::new(start.x.max(tile.x), start.y.max(tile.y)),
+        ));
+      }
+    },
+    DesignationMode::Cancel => {
+      let designated = targets.scrap.iter().chain(targets.buildings.iter()).chain(targets.items.iter());
+      for (entity, _) in designated.filter(|(_, position)| inside(position)) {
+        commands.entity(entity).remove::<(Designation, Forbidden)>();
+      }
+
+      // Whatever was already delivered to a cancelled blueprint is left on the floor
+      let impassable_set: HashSet<Entity> = targets.impassable.iter().collect();
+      for (entity, position, input) in targets.blueprints.iter().filter(|(_, position, _)| inside(position)) {
+        for (item, count) in &input.0.items {
+          targets.floor.drop_near(&mut commands, &impassable_set, item, *count, position);
+        }
+        commands.entity(entity).despawn();
+      }
+    },
+    DesignationMode::Wall | DesignationMode::Door | DesignationMode::Conduit | DesignationMode::Generator
+      | DesignationMode::Charger | DesignationMode::Conveyor | DesignationMode::Inserter
+      | DesignationMode::Lamp | DesignationMode::SolarPanel => {
+      let Some(name) = tool.mode.building() else {
+        return;
+      };
+      if !targets.research.is_unlocked(&Unlock::Building(name.to_string()), &targets.archetypes) {
+        info!("Research hasn't unlocked {} yet", name);
+        return;
+      }
+
+      // Only on empty floor: nothing built, planned, mined or lying there
+      let occupied: HashSet<Position> = targets.buildings.iter().chain(targets.scrap.iter()).chain(targets.items.iter())
+        .chain(targets.blueprints.iter().map(|(entity, position, _)| (entity, position)))
+        .map(|(_, position)| *position)
+        .collect();
+      for x in start.x.min(tile.x)..=start.x.max(tile.x) {
+        for y in start.y.min(tile.y)..=start.y.max(tile.y) {
+          let position = Position::new(x, y);
+          if !occupied.contains(&position) {
+            spawn_blueprint(&mut commands, &targets.archetypes, name, position, tool.heading);
+          }
+        }
+      }
+    },
+  }
+}
+
+pub fn draw_designations(
+  grid: Res<Grid>,
+  tool: Res<DesignationTool>,
+  cursor: Cursor,
+  designated: Query<(&Position, &Designation)>,
+  forbidden: Query<&Position, With<Forbidden>>,
+  blueprints: Query<&Position, With<Blueprint>>,
+  mut gizmos: Gizmos,
+) {
+  let half = grid.tile_size * 0.4;
+
+  for (position, designation) in designated.iter() {
+    let center = grid.world_position(position);
+    let color = match designation {
+      Designation::Mine => DesignationMode::Mine.color(),
+      Designation::Deconstruct => DesignationMode::Deconstruct.color(),
+    };
+    gizmos.rect_2d(center, 0.0, Vec2::splat(half * 2.0), color);
+  }
+
+  for position in forbidden.iter() {
+    let center = grid.world_position(position);
+    gizmos.line_2d(center + Vec2::new(-half, -half), center + Vec2::new(half, half), DesignationMode::Forbid.color());
+  }
+
+  for position in blueprints.iter() {
+    let center = grid.world_position(position);
+    gizmos.rect_2d(center, 0.0, Vec2::splat(grid.tile_size), DesignationMode::Wall.color());
+  }
+
+  if let (Some(start), Some(tile)) = (tool.drag_start, cursor.tile()) {
+    let a = grid.world_position(&start);
+    let b = grid.world_position(&tile);
+    let size = (b - a).abs() + Vec2::splat(grid.tile_size);
+    gizmos.rect_2d((a + b) / 2.0, 0.0, size, tool.mode.color());
+  }
+}
+
+pub fn spawn_tool_indicator(mut commands: Commands) {
+  commands.spawn((
+    TextBundle::from_section("", TextStyle {
+      font_size: 14.0,
+      color: Color::WHITE,
+      ..default()
+    }).with_style(Style {
+      position_type: PositionType::Absolute,
+      bottom: Val::Px(8.0),
+      right: Val::Px(8.0),
+      ..default()
+    }),
+    ToolIndicator,
+  ));
+}
+
+pub fn update_tool_indicator(
+  tool: Res<DesignationTool>,
+  mut indicators: Query<&mut Text, With<ToolIndicator>>,
+) {
+  if !tool.is_changed() {
+    return;
+  }
+
+  for mut text in indicators.iter_mut() {
+    text.sections[0].value = format!(
+      "Tool: {} (facing {})  Mining: {} {}",
+      tool.mode.label(),
+      tool.heading.label(),
+      if tool.require_designation { "designated" } else { "all" },
+      tool.mining_target.as_deref().unwrap_or("resources"),
+    );
+  }
+}
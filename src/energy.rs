@@ -0,0 +1,46 @@
+use bevy::prelude::*;
+use crate::grid::Position;
+use crate::entities::bot::Bot;
+
+/// Energy a bot spends crossing one tile
+const ENERGY_PER_TILE: u32 = 1;
+/// Below this share of capacity a bot goes to recharge
+const LOW_ENERGY_PERCENT: u32 = 25;
+
+/// Charge held in a bot's battery
+#[derive(Component, Debug)]
+pub struct Energy {
+  pub charge: u32,
+  pub capacity: u32,
+}
+
+impl Energy {
+  pub fn full(capacity: u32) -> Self {
+    Self { charge: capacity, capacity }
+  }
+
+  pub fn set_capacity(&mut self, capacity: u32) {
+    self.capacity = capacity;
+    self.charge = self.charge.min(capacity);
+  }
+
+  /// Whether the bot should look for a charger
+  pub fn is_low(&self) -> bool {
+    self.charge * 100 < self.capacity * LOW_ENERGY_PERCENT
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.charge >= self.capacity
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.charge == 0
+  }
+}
+
+/// Bots drain their batteries as they move
+pub fn drain_energy(mut bots: Query<&mut Energy, (With<Bot>, Changed<Position>)>) {
+  for mut energy in bots.iter_mut() {
+    energy.charge = energy.charge.saturating_sub(ENERGY_PER_TILE);
+  }
+}
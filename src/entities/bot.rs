@@ -1,106 +1,1090 @@
 use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use crate::grid::{Position, Grid, Impassable};
 use crate::entities::scrap::Scrap;
+use crate::entities::building::{self, Building, Blueprint, Fabricator, BotFactory, Workshop, Lab, Locked};
+use crate::research::Research;
 use crate::reservation::{ReservationSystem, ReservationKey};
 use crate::pathfinding::{Path, distance};
 use crate::interact::Interaction;
+use crate::archetype::{Archetype, Archetypes};
+use crate::colony::{ColonyStats, Alerts, AlertKind};
+use crate::simulation::SimulationClock;
+use crate::schedule::Activity;
+use crate::inventory::{Cargo, Storage, InputBuffer, OutputBuffer};
+use crate::items::{self, Item, Floor};
+use crate::zones::{self, StockpileZone};
+use crate::jobs::{Job, JobKind, JobType, Dropoff};
+use crate::modules::{Modules, ModuleSlot};
+use crate::health::{Health, Broken, REPAIR_TICKS};
+use crate::hostiles::Hostile;
+use crate::events::Rubble;
+use crate::fog::FogOfWar;
+use crate::designation::{Designation, DesignationTool, Forbidden, DECONSTRUCT_TICKS};
+use crate::groups::{Group, BotGroups, GroupSettings};
+use crate::impass::{self, Door};
+use crate::spawn::spawn_building;
+use crate::energy::Energy;
+use crate::power::Charger;
+use crate::conveyors::{Conveyor, Heading};
 
 #[derive(Component)]
 pub struct Bot {
-    /// The current reservation this bot is working on (if any)
-    pub current_reservation: Option<ReservationKey>,
+    /// The job this bot is working on (if any)
+    pub job: Option<Job>,
+    /// Interaction ticks completed per fixed tick of work
+    pub work_rate: u32,
+    /// Damage dealt to a nearby hostile per attack, from weapon modules
+    pub attack: u32,
 }
 
 impl Bot {
-    pub fn new() -> Self {
+    pub fn new(work_rate: u32) -> Self {
         Self {
-            current_reservation: None
+            job: None,
+            work_rate: work_rate.max(1),
+            attack: 0,
         }
     }
 }
 
+/// Buildings and blueprints whose input buffer bots can supply
+type Consumer = (&'static InputBuffer, Option<&'static Fabricator>, Option<&'static BotFactory>, Option<&'static Blueprint>);
+/// Health of anything bots can repair, and whether it is a bot that is broken
+/// or still on its way somewhere
+type Repairable = (&'static Health, Has<Bot>, Has<Broken>, Has<Path>);
+type Deconstructable = Or<(With<Building>, With<Rubble>)>;
+
+/// Everything job search looks at besides the bots themselves
+#[derive(SystemParam)]
+pub struct JobTargets<'w, 's> {
+    grid: Res<'w, Grid>,
+    archetypes: Res<'w, Archetypes>,
+    research: Res<'w, Research>,
+    tool: Res<'w, DesignationTool>,
+    fog: Res<'w, FogOfWar>,
+    groups: Res<'w, BotGroups>,
+    clock: Res<'w, SimulationClock>,
+    positions: Query<'w, 's, &'static Position>,
+    impassable: Query<'w, 's, Entity, With<Impassable>>,
+    scrap: Query<'w, 's, (&'static Scrap, Option<&'static Archetype>, Option<&'static Designation>)>,
+    items: Query<'w, 's, (&'static Item, &'static Position)>,
+    forbidden: Query<'w, 's, (), With<Forbidden>>,
+    belts: Query<'w, 's, (), With<Conveyor>>,
+    deconstructable: Query<'w, 's, &'static Designation, Deconstructable>,
+    zones: Query<'w, 's, &'static StockpileZone>,
+    storages: Query<'w, 's, &'static Storage>,
+    producers: Query<'w, 's, Consumer, Without<Locked>>,
+    blueprints: Query<'w, 's, (&'static Blueprint, &'static InputBuffer)>,
+    doors: Query<'w, 's, (Entity, &'static Position, &'static Door)>,
+    repairable: Query<'w, 's, Repairable, Without<Hostile>>,
+    outputs: Query<'w, 's, &'static OutputBuffer>,
+    workshops: Query<'w, 's, Entity, (With<Workshop>, Without<Locked>)>,
+    labs: Query<'w, 's, Entity, (With<Lab>, Without<Locked>)>,
+    chargers: Query<'w, 's, Entity, (With<Charger>, Without<Locked>)>,
+}
+
+/// The idle bot a job is being looked for
+struct Seeker<'a> {
+    entity: Entity,
+    position: &'a Position,
+    cargo: &'a Cargo,
+    modules: &'a Modules,
+    energy: &'a Energy,
+    /// Settings of the bot's group, if it is in one that exists
+    group: Option<&'a GroupSettings>,
+    /// What the group's timetable has the bot doing this hour
+    activity: Activity,
+    /// Entities the bot can reach on explored tiles inside its group's area,
+    /// plus the group's home, nearest first
+    reachable: Vec<Entity>,
+    impassable: &'a HashSet<Entity>,
+}
+
+impl Seeker<'_> {
+    /// Whether the bot's group lets it take this kind of job
+    fn does(&self, job: JobType) -> bool {
+        self.group.is_none_or(|group| group.does(job))
+    }
+
+    fn in_area(&self, position: &Position) -> bool {
+        self.group.is_none_or(|group| group.covers(position))
+    }
+
+    fn home(&self) -> Option<Entity> {
+        self.group.and_then(|group| group.home)
+    }
+}
+
+type SeekerParts = (Entity, &'static mut Bot, &'static Position, &'static Cargo, &'static Modules, &'static Energy, Option<&'static Group>);
+
+/// Assigns a job to every idle bot. In order of preference: recharge a low
+/// battery, deliver what it carries, install a module, empty a building's
+/// output, supply a building's input, build, repair, deconstruct, store loose
+/// items, research, mine, explore. Only what stands on explored tiles is
+/// considered, and only inside the bot's group area and of the job types its
+/// group allows. Off its timetable's work hours a bot only charges, delivers
+/// and goes home. Broken bots and bots waiting for a repair take no jobs, and
+/// a bot with a flat battery takes nothing but a charge job.
 pub fn find_bot_jobs(
     mut reservations: ResMut<ReservationSystem>,
-    grid: Res<Grid>,
-    impassable: Query<Entity, With<Impassable>>,
-    scrap: Query<Entity, With<Scrap>>,
-    mut bots: Query<(Entity, &mut Bot, &Position)>,
+    targets: JobTargets,
+    mut bots: Query<SeekerParts, Without<Broken>>,
 ) {
-    let impassable_set: std::collections::HashSet<Entity> = impassable.iter().collect();
-
-    for (bot_entity, mut bot, bot_position) in bots.iter_mut() {
-        if bot.current_reservation.is_none() {
-            let reachable_entities = grid.flood_search(bot_position, &impassable_set);
-
-            let reachable_scrap: Vec<Entity> = reachable_entities
-                .into_iter()
-                .filter(|entity| {
-                  return scrap.contains(*entity);
-                })
-                .collect();
-
-            for scrap_entity in reachable_scrap {
-                let key = ReservationKey::Entity(scrap_entity);
-
-                if reservations.is_reserved(&key) {
-                    continue;
-                }
-
-                if reservations.try_reserve(key.clone(), bot_entity) {
-                  info!("Bot {:?} reserved scrap {:?}. Key: {:?}", bot_entity, scrap_entity, key);
-                    bot.current_reservation = Some(key);
-                    break;
-                }
+    let impassable_set: HashSet<Entity> = targets.impassable.iter().collect();
+
+    for (bot_entity, mut bot, bot_position, cargo, modules, energy, group) in bots.iter_mut() {
+        if bot.job.is_some() || reservations.is_reserved(&ReservationKey::Entity(bot_entity)) {
+            continue;
+        }
+
+        // Doors locked to the bot's group are as good as walls to it
+        let blocked = impass::blocked_for(group, &impassable_set, &targets.doors);
+        let settings = group.and_then(|group| targets.groups.get(&group.0));
+        let activity = settings.map_or(Activity::Work, |group| group.schedule.at(targets.clock.hour()));
+
+        let seeker = Seeker {
+            entity: bot_entity,
+            position: bot_position,
+            cargo,
+            modules,
+            energy,
+            group: settings,
+            activity,
+            reachable: targets.grid.flood_search(bot_position, &blocked).into_iter()
+                .filter(|entity| targets.positions.get(*entity).is_ok_and(|position| {
+                    targets.fog.is_explored(position)
+                        && (settings.is_none_or(|group| group.home == Some(*entity) || group.covers(position)))
+                }))
+                .collect(),
+            impassable: &blocked,
+        };
+
+        // A flat battery leaves the bot nothing to do but find a charger, and
+        // off shift it only puts away what it carries and heads home
+        let job = charge_job(&seeker, &targets, &mut reservations).or_else(|| if energy.is_empty() {
+            None
+        } else if activity != Activity::Work {
+            if cargo.0.is_empty() {
+                go_home_job(&seeker, &targets)
+            } else {
+                deliver_cargo_job(&seeker, &targets, &mut reservations)
             }
+        } else if cargo.0.is_empty() {
+            install_module_job(&seeker, &targets, &mut reservations)
+                .or_else(|| empty_output_job(&seeker, &targets, &mut reservations))
+                .or_else(|| supply_input_job(&seeker, &targets, &mut reservations))
+                .or_else(|| build_job(&seeker, &targets, &mut reservations))
+                .or_else(|| repair_job(&seeker, &targets, &mut reservations))
+                .or_else(|| deconstruct_job(&seeker, &targets, &mut reservations))
+                .or_else(|| store_item_job(&seeker, &targets, &mut reservations))
+                .or_else(|| research_job(&seeker, &targets, &mut reservations))
+                .or_else(|| mine_job(&seeker, &targets, &mut reservations))
+                .or_else(|| explore_job(&seeker, &targets, &mut reservations))
+        } else {
+            deliver_cargo_job(&seeker, &targets, &mut reservations)
+        });
+
+        if let Some(job) = job {
+            debug!("Bot {:?} took job {}", bot_entity, job.label());
+            bot.job = Some(job);
         }
     }
 }
 
+/// Reachable loose items no other bot has claimed and the player hasn't forbidden, nearest first
+fn unclaimed_items<'a>(
+  seeker: &'a Seeker,
+  targets: &'a JobTargets,
+  reservations: &'a ReservationSystem,
+) -> impl Iterator<Item = (Entity, &'a Item, &'a Position)> + 'a {
+  seeker.reachable.iter()
+    .filter(|entity| !reservations.is_reserved(&ReservationKey::Entity(**entity)))
+    .filter(|entity| !targets.forbidden.contains(**entity))
+    .filter_map(|entity| targets.items.get(*entity).ok().map(|(item, position)| (*entity, item, position)))
+    // Stacks riding a belt are on their way somewhere already
+    .filter(|(_, _, position)| !targets.grid.residents(position).iter().any(|resident| targets.belts.contains(*resident)))
+}
+
+/// The nearest storage building or unclaimed loose item holding `item`, how
+/// many it holds, and the reservations taking from it needs.
+fn find_source(
+  seeker: &Seeker,
+  item: &str,
+  targets: &JobTargets,
+  reservations: &ReservationSystem,
+) -> Option<(Entity, u32, Vec<ReservationKey>)> {
+  let stored = seeker.reachable.iter()
+    .filter_map(|entity| targets.storages.get(*entity).ok().map(|storage| (*entity, storage.0.count(item))))
+    .find(|(_, count)| *count > 0)
+    .map(|(entity, count)| (entity, count, Vec::new()));
+
+  stored.or_else(|| {
+    unclaimed_items(seeker, targets, reservations)
+      .find(|(_, loose, _)| loose.name == item)
+      .map(|(entity, loose, _)| (entity, loose.count, vec![ReservationKey::Entity(entity)]))
+  })
+}
+
+/// Where to put `item` away: the closest free zone tile or the group's home
+/// depot, whichever is nearer, else the nearest storage building with space.
+/// Also gives the room there and the reservations the drop needs.
+fn store_dropoff(
+  seeker: &Seeker,
+  item: &str,
+  targets: &JobTargets,
+  reservations: &ReservationSystem,
+) -> Option<(Dropoff, u32, Vec<ReservationKey>)> {
+  let zone = zones::nearest_free_zone_tile(
+    seeker.position, item, &targets.grid, seeker.impassable, &targets.zones, reservations, room_on_tile(item, targets),
+  ).map(|(tile, room)| (Dropoff::Tile(tile), room, vec![ReservationKey::Tile(tile)], distance(seeker.position, &tile)));
+  let home = seeker.home()
+    .filter(|home| seeker.reachable.contains(home))
+    .and_then(|home| targets.storages.get(home).ok().map(|storage| (home, storage.0.free_space())))
+    .filter(|(_, space)| *space > 0)
+    .and_then(|(home, space)| {
+      let position = targets.positions.get(home).ok()?;
+      Some((Dropoff::Building(home), space, Vec::new(), distance(seeker.position, position)))
+    });
+
+  // The home depot wins a tie with a zone
+  let nearest = [home, zone].into_iter().flatten().min_by(|a, b| a.3.total_cmp(&b.3));
+  if let Some((dropoff, room, keys, _)) = nearest {
+    return Some((dropoff, room, keys));
+  }
+
+  seeker.reachable.iter()
+    .filter_map(|entity| targets.storages.get(*entity).ok().map(|storage| (*entity, storage.0.free_space())))
+    .find(|(_, space)| *space > 0)
+    .map(|(storage, space)| (Dropoff::Building(storage), space, Vec::new()))
+}
+
+fn deliver_cargo_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  let (item, carried) = seeker.cargo.0.first_item()?;
+
+  for entity in &seeker.reachable {
+    let Ok((input, fabricator, factory, blueprint)) = targets.producers.get(*entity) else {
+      continue;
+    };
+
+    let requirements = building::input_requirements(fabricator, factory, blueprint, &targets.archetypes, &targets.research);
+    let wanted = building::wanted(&requirements, item, &input.0).min(carried);
+    if wanted == 0 {
+      continue;
+    }
+
+    let mut job = Job::new(JobKind::Haul {
+      source: None,
+      destination: Dropoff::Building(*entity),
+      item: item.to_string(),
+      amount: wanted,
+      picked_up: true,
+    });
+    if job.try_reserve(vec![ReservationKey::Input(*entity)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  // With nowhere to store it, set the cargo down on the nearest tile with room
+  let (destination, space, keys) = store_dropoff(seeker, item, targets, reservations)
+    .or_else(|| floor_dropoff(seeker, item, targets, reservations))?;
+  let mut job = Job::new(JobKind::Haul {
+    source: None,
+    destination,
+    item: item.to_string(),
+    amount: carried.min(space),
+    picked_up: true,
+  });
+  job.try_reserve(keys, seeker.entity, reservations).then_some(job)
+}
+
+/// How many of `item` fit on a tile given the stack lying there
+fn room_on_tile<'a>(item: &'a str, targets: &'a JobTargets) -> impl Fn(&Position) -> u32 + 'a {
+  let stack_size = targets.archetypes.stack_size(item);
+  move |position| {
+    let stack = targets.grid.residents(position).iter()
+      .find_map(|entity| targets.items.get(*entity).ok().map(|(stack, _)| stack));
+    items::room_on_tile(item, stack_size, stack)
+  }
+}
+
+/// The nearest tile, zone or not, with room for `item` that no bot is bringing items to
+fn floor_dropoff(
+  seeker: &Seeker,
+  item: &str,
+  targets: &JobTargets,
+  reservations: &ReservationSystem,
+) -> Option<(Dropoff, u32, Vec<ReservationKey>)> {
+  let room = room_on_tile(item, targets);
+  let tile = targets.grid.nearest_tile(seeker.position, seeker.impassable, |position| {
+    !reservations.is_reserved(&ReservationKey::Tile(*position)) && room(position) > 0
+  })?;
+  Some((Dropoff::Tile(tile), room(&tile), vec![ReservationKey::Tile(tile)]))
+}
+
+fn empty_output_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Haul) {
+    return None;
+  }
+
+  for entity in &seeker.reachable {
+    let Ok(output) = targets.outputs.get(*entity) else {
+      continue;
+    };
+    let Some((item, count)) = output.0.first_item() else {
+      continue;
+    };
+    let Some((destination, space, mut keys)) = store_dropoff(seeker, item, targets, reservations) else {
+      continue;
+    };
+
+    let mut job = Job::new(JobKind::Haul {
+      source: Some(*entity),
+      destination,
+      item: item.to_string(),
+      amount: count.min(seeker.cargo.0.free_space()).min(space),
+      picked_up: false,
+    });
+    keys.push(ReservationKey::Output(*entity));
+    if job.try_reserve(keys, seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+fn supply_input_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Haul) {
+    return None;
+  }
+
+  let mut stock: BTreeMap<String, u32> = BTreeMap::new();
+  for storage in seeker.reachable.iter().filter_map(|entity| targets.storages.get(*entity).ok()) {
+    for (item, count) in &storage.0.items {
+      *stock.entry(item.clone()).or_insert(0) += count;
+    }
+  }
+  for (_, loose, _) in unclaimed_items(seeker, targets, reservations) {
+    *stock.entry(loose.name.clone()).or_insert(0) += loose.count;
+  }
+
+  for entity in &seeker.reachable {
+    let Ok((input, fabricator, factory, blueprint)) = targets.producers.get(*entity) else {
+      continue;
+    };
+    let requirements = building::input_requirements(fabricator, factory, blueprint, &targets.archetypes, &targets.research);
+    let Some((item, needed)) = building::demand(&requirements, &input.0, &stock) else {
+      continue;
+    };
+    let Some((source, available, mut keys)) = find_source(seeker, &item, targets, reservations) else {
+      continue;
+    };
+
+    let mut job = Job::new(JobKind::Haul {
+      source: Some(source),
+      destination: Dropoff::Building(*entity),
+      amount: needed.min(available).min(seeker.cargo.0.free_space()),
+      item,
+      picked_up: false,
+    });
+    keys.push(ReservationKey::Input(*entity));
+    if job.try_reserve(keys, seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Carries a loose item that isn't in an accepting zone to one, or into storage
+fn store_item_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Haul) {
+    return None;
+  }
+
+  let loose: Vec<(Entity, String, u32)> = unclaimed_items(seeker, targets, reservations)
+    .filter(|(_, item, position)| !zones::is_stored(position, &item.name, &targets.zones))
+    .map(|(entity, item, _)| (entity, item.name.clone(), item.count))
+    .collect();
+
+  for (entity, item, count) in loose {
+    let Some((destination, space, mut keys)) = store_dropoff(seeker, &item, targets, reservations) else {
+      continue;
+    };
+
+    let mut job = Job::new(JobKind::Haul {
+      source: Some(entity),
+      destination,
+      amount: count.min(seeker.cargo.0.free_space()).min(space),
+      item,
+      picked_up: false,
+    });
+    keys.push(ReservationKey::Entity(entity));
+    if job.try_reserve(keys, seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Fetches a stored module for one of the bot's free slots and has it installed
+fn install_module_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Install) {
+    return None;
+  }
+
+  let archetypes = &targets.archetypes;
+  let fits = |item: &str| {
+    targets.research.module_unlocked(item, archetypes)
+      && archetypes.module(item).is_some_and(|module| seeker.modules.is_free(module.slot))
+  };
+
+  let stored = seeker.reachable.iter()
+    .filter_map(|entity| targets.storages.get(*entity).ok())
+    .find_map(|storage| storage.0.items.keys().find(|item| fits(item)).cloned());
+  let module = stored.or_else(|| {
+    unclaimed_items(seeker, targets, reservations)
+      .find(|(_, item, _)| fits(&item.name))
+      .map(|(_, item, _)| item.name.clone())
+  })?;
+  let (source, _, keys) = find_source(seeker, &module, targets, reservations)?;
+
+  for workshop in seeker.reachable.iter().filter(|entity| targets.workshops.contains(**entity)) {
+    let mut job = Job::new(JobKind::Install {
+      source,
+      workshop: *workshop,
+      module: module.clone(),
+      picked_up: false,
+    });
+    let mut keys = keys.clone();
+    keys.push(ReservationKey::Entity(*workshop));
+    if job.try_reserve(keys, seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+fn research_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Research) {
+    return None;
+  }
+
+  targets.research.current()?;
+
+  for lab in seeker.reachable.iter().filter(|entity| targets.labs.contains(**entity)) {
+    let mut job = Job::new(JobKind::Research { lab: *lab });
+    if job.try_reserve(vec![ReservationKey::Entity(*lab)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Sends a bot low on energy, or on a charge shift without a full battery, to
+/// the nearest free charger
+fn charge_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  let topping_up = seeker.activity == Activity::Charge && !seeker.energy.is_full();
+  if !seeker.energy.is_low() && !topping_up {
+    return None;
+  }
+
+  for charger in seeker.reachable.iter().filter(|entity| targets.chargers.contains(**entity)) {
+    let mut job = Job::new(JobKind::Charge { charger: *charger });
+    if job.try_reserve(vec![ReservationKey::Entity(*charger)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Sends a bot that isn't next to its group's home back there
+fn go_home_job(seeker: &Seeker, targets: &JobTargets) -> Option<Job> {
+  let home = seeker.home().filter(|home| seeker.reachable.contains(home))?;
+  let home_position = targets.positions.get(home).ok()?;
+  (distance(seeker.position, home_position) > 1.0).then(|| Job::new(JobKind::GoHome { home }))
+}
+
+/// Builds the nearest blueprint whose whole cost has been delivered
+fn build_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Build) {
+    return None;
+  }
+
+  let supplied = seeker.reachable.iter().filter(|entity| match targets.blueprints.get(**entity) {
+    Ok((blueprint, input)) => blueprint.is_supplied(&input.0, &targets.archetypes),
+    Err(_) => false,
+  });
+
+  for site in supplied {
+    let mut job = Job::new(JobKind::Build { site: *site });
+    if job.try_reserve(vec![ReservationKey::Entity(*site)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Repairs the nearest damaged building, broken bot or damaged bot that is
+/// standing still. A bot being repaired waits for it to finish.
+fn repair_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Repair) {
+    return None;
+  }
+
+  let damaged = seeker.reachable.iter()
+    .filter(|entity| **entity != seeker.entity)
+    .filter(|entity| match targets.repairable.get(**entity) {
+      Ok((health, is_bot, broken, moving)) => health.needs_repair() && (!is_bot || broken || !moving),
+      Err(_) => false,
+    });
+
+  for target in damaged {
+    let mut job = Job::new(JobKind::Repair { target: *target });
+    if job.try_reserve(vec![ReservationKey::Entity(*target)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+fn deconstruct_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Deconstruct) {
+    return None;
+  }
+
+  let designated = seeker.reachable.iter()
+    .filter(|entity| targets.deconstructable.get(**entity).is_ok_and(|designation| *designation == Designation::Deconstruct));
+
+  for building in designated {
+    let mut job = Job::new(JobKind::Deconstruct { target: *building });
+    if job.try_reserve(vec![ReservationKey::Entity(*building)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Mines the nearest resource node the bot's tools can handle. The player can
+/// limit mining to designated nodes and to a single resource type.
+fn mine_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Mine) {
+    return None;
+  }
+
+  let tool_tier = seeker.modules.tool_tier(&targets.archetypes);
+  let minable = seeker.reachable.iter().filter(|entity| match targets.scrap.get(**entity) {
+    Ok((node, archetype, designation)) => {
+      node.tier <= tool_tier
+        && (!targets.tool.require_designation || designation == Some(&Designation::Mine))
+        && targets.tool.mining_target.as_ref()
+          .is_none_or(|wanted| archetype.is_some_and(|archetype| archetype.0 == *wanted))
+    },
+    Err(_) => false,
+  });
+
+  for scrap_entity in minable {
+    let mut job = Job::new(JobKind::Mine { target: *scrap_entity });
+    if job.try_reserve(vec![ReservationKey::Entity(*scrap_entity)], seeker.entity, reservations) {
+      return Some(job);
+    }
+  }
+
+  None
+}
+
+/// Sends the bot to the nearest frontier tile no other bot is exploring
+fn explore_job(
+  seeker: &Seeker,
+  targets: &JobTargets,
+  reservations: &mut ReservationSystem,
+) -> Option<Job> {
+  if !seeker.does(JobType::Explore) {
+    return None;
+  }
+
+  let target = targets.grid.nearest_tile(seeker.position, seeker.impassable, |position| {
+    targets.fog.is_frontier(position, &targets.grid)
+      && seeker.in_area(position)
+      && !reservations.is_reserved(&ReservationKey::Tile(*position))
+  })?;
+
+  let mut job = Job::new(JobKind::Explore { target });
+  job.try_reserve(vec![ReservationKey::Tile(target)], seeker.entity, reservations).then_some(job)
+}
+
+/// Anything that can stand in the way of a building going up
+type Occupant = Or<(With<Bot>, With<Hostile>)>;
+
+/// Everything bots work on besides themselves
+#[derive(SystemParam)]
+pub struct WorkTargets<'w, 's> {
+  commands: Commands<'w, 's>,
+  grid: Res<'w, Grid>,
+  archetypes: Res<'w, Archetypes>,
+  research: ResMut<'w, Research>,
+  stats: ResMut<'w, ColonyStats>,
+  alerts: ResMut<'w, Alerts>,
+  clock: Res<'w, SimulationClock>,
+  positions: Query<'w, 's, &'static Position, Without<Bot>>,
+  scrap: Query<'w, 's, (&'static Position, &'static mut Scrap, Option<&'static Archetype>)>,
+  labs: Query<'w, 's, &'static Lab>,
+  blueprints: Query<'w, 's, (&'static Blueprint, Option<&'static Heading>)>,
+  chargers: Query<'w, 's, &'static Charger>,
+  healths: Query<'w, 's, &'static mut Health>,
+  storages: Query<'w, 's, &'static mut Storage>,
+  inputs: Query<'w, 's, &'static mut InputBuffer>,
+  outputs: Query<'w, 's, &'static mut OutputBuffer>,
+  floor: Floor<'w, 's>,
+  impassable: Query<'w, 's, Entity, With<Impassable>>,
+  /// Bots and hostiles, and whether they are on their way somewhere
+  occupants: Query<'w, 's, Has<Path>, Occupant>,
+}
+
+/// The bot working a job, and the parts of it the job can use up or change
+struct Worker<'a> {
+  entity: Entity,
+  position: &'a Position,
+  work_rate: u32,
+  path: Option<&'a Path>,
+  interaction: Option<&'a Interaction>,
+  cargo: Mut<'a, Cargo>,
+  modules: Mut<'a, Modules>,
+  energy: Mut<'a, Energy>,
+}
+
+impl Worker<'_> {
+  /// Sends the bot toward `target_position` and returns whether it is already adjacent
+  fn walk_to(&self, commands: &mut Commands, target_position: &Position) -> bool {
+    if distance(self.position, target_position) <= 1.0 {
+      return true;
+    }
+
+    if self.path.is_none() {
+      commands.entity(self.entity).insert(Path::new(*target_position));
+    }
+    false
+  }
+
+  /// Walks to the target and works an `Interaction` with it, returning whether
+  /// the interaction has completed. `powered` work goes at the speed the
+  /// target's power network allows.
+  fn work_at(&self, commands: &mut Commands, target_position: &Position, target_entity: Entity, ticks: u32, powered: bool) -> bool {
+    if self.walk_to(commands, target_position) {
+      let Some(interaction) = self.interaction else {
+        debug!("Bot {:?} is ready to work on {:?}", self.entity, target_entity);
+        let interaction = Interaction::new(self.entity, ticks);
+        commands.entity(self.entity).insert(if powered { interaction.powered_by(target_entity) } else { interaction });
+        return false;
+      };
+
+      if interaction.completed {
+        debug!("Bot {:?} has completed interaction with {:?}", self.entity, target_entity);
+        commands.entity(self.entity).remove::<Interaction>();
+        commands.entity(self.entity).remove::<Path>();
+        return true;
+      }
+    }
+
+    false
+  }
+}
+
+type WorkerParts = (
+  Entity,
+  &'static mut Bot,
+  &'static Position,
+  &'static mut Cargo,
+  &'static mut Modules,
+  &'static mut Energy,
+  Option<&'static Path>,
+  Option<&'static Interaction>,
+);
+
+/// Advances every bot's job by one step and releases the job once it is done
 pub fn work(
-  bots: Query<(Entity, &Bot, &Position, Option<&Path>, Option<&Interaction>)>,
-  scrap: Query<&Position, With<Scrap>>,
-  mut commands: Commands
+  mut bots: Query<WorkerParts>,
+  mut targets: WorkTargets,
+  mut reservations: ResMut<ReservationSystem>,
 ) {
-  for (bot_entity, bot, bot_position, path, interaction) in bots.iter() {
-    let Some(reservation_key) = &bot.current_reservation else {
+  // Other bots can be repair targets, and the loop below holds the bot query
+  let bot_positions: HashMap<Entity, Position> = bots.iter().map(|(entity, _, position, ..)| (entity, *position)).collect();
+
+  for (entity, mut bot, position, cargo, modules, energy, path, interaction) in bots.iter_mut() {
+    let mut worker = Worker { entity, position, work_rate: bot.work_rate, path, interaction, cargo, modules, energy };
+    let Some(job) = bot.job.as_mut() else {
       continue;
     };
 
-    match reservation_key {
-      ReservationKey::Tile(_tile_pos) => {
-        warn!("Bot {:?} has a tile reservation {:?}. This should not happen.", bot_entity, reservation_key);
+    let finished = match &mut job.kind {
+      JobKind::Mine { target } => work_mine(&mut worker, &mut targets, *target),
+      JobKind::Haul { source, destination, item, amount, picked_up } => {
+        work_haul(&mut worker, &mut targets, *source, *destination, item, amount, picked_up)
       },
-      ReservationKey::Entity(scrap_entity) => {
-        if let Ok(scrap_position) = scrap.get(*scrap_entity) {
-          work_on_scrap(&mut commands, bot_entity, bot_position, scrap_position, *scrap_entity, path, interaction);
-        } else {
-          warn!("Bot {:?} has a non-scrap reservation {:?}. This should not happen.", bot_entity, reservation_key);
-        }
+      JobKind::Research { lab } => work_research(&worker, &mut targets, *lab),
+      JobKind::Charge { charger } => work_charge(&mut worker, &mut targets, *charger),
+      JobKind::Deconstruct { target } => work_deconstruct(&worker, &mut targets, *target),
+      JobKind::Build { site } => work_build(&worker, &mut targets, *site),
+      JobKind::Repair { target } => work_repair(&worker, &mut targets, *target, &bot_positions),
+      JobKind::Explore { target } => worker.walk_to(&mut targets.commands, target),
+      JobKind::GoHome { home } => match targets.positions.get(*home) {
+        Ok(home_position) => worker.walk_to(&mut targets.commands, home_position),
+        Err(_) => true,
+      },
+      JobKind::Install { source, workshop, module, picked_up } => {
+        work_install(&mut worker, &mut targets, *source, *workshop, module, picked_up)
       },
+    };
+
+    if finished {
+      if let Some(job) = bot.job.take() {
+        job.release(&mut reservations);
+      }
+      targets.commands.entity(entity).remove::<Path>();
+      if interaction.is_some() {
+        targets.commands.entity(entity).remove::<Interaction>();
+      }
+    }
+  }
+}
+
+/// Mines a session's worth off a resource node and leaves it on the floor.
+/// Only what fits on the floor nearby comes off the node, and a session that
+/// can't drop anything fails with an alert rather than being repeated.
+fn work_mine(worker: &mut Worker, targets: &mut WorkTargets, target: Entity) -> bool {
+  let Ok((scrap_position, mut scrap_data, archetype)) = targets.scrap.get_mut(target) else {
+    warn!("Bot {:?} is mining {:?}, which no longer exists", worker.entity, target);
+    return true;
+  };
+
+  let ticks = scrap_data.session_ticks(worker.work_rate);
+  let mined = worker.work_at(&mut targets.commands, scrap_position, target, ticks, false);
+  if mined {
+    // A session breaks off the resource's yield, at most what the bot
+    // could carry, and leaves it on the floor
+    let item = archetype.map_or("scrap", |archetype| archetype.0.as_str());
+    let carry = worker.cargo.0.capacity.max(1);
+    let amount = scrap_data.size.min(scrap_data.yield_per_session.map_or(carry, |amount| amount.min(carry)));
+    let impassable_set: HashSet<Entity> = targets.impassable.iter().collect();
+    let amount = targets.floor.drop_near(&mut targets.commands, &impassable_set, item, amount, worker.position);
+    if amount == 0 {
+      let message = format!("No room to drop {} mined at ({}, {})", item, scrap_position.x, scrap_position.y);
+      targets.alerts.raise(AlertKind::NoRoomToDrop(target), message, targets.clock.tick);
+      return true;
+    }
+    targets.alerts.clear(&AlertKind::NoRoomToDrop(target));
+    scrap_data.size -= amount;
+    targets.stats.record_mined(amount, targets.clock.tick);
+
+    if let Some(drill) = worker.modules.wear_out(ModuleSlot::Drill, &targets.archetypes) {
+      info!("Bot {:?} wore out its {}", worker.entity, drill);
+    }
+
+    if scrap_data.size == 0 {
+      targets.commands.entity(target).despawn();
     }
   }
+  mined
 }
 
-fn work_on_scrap(
+/// Picks items up at the source, then carries them to the destination
+fn work_haul(
+  worker: &mut Worker,
+  targets: &mut WorkTargets,
+  source: Option<Entity>,
+  destination: Dropoff,
+  item: &str,
+  amount: &mut u32,
+  picked_up: &mut bool,
+) -> bool {
+  if !*picked_up {
+    let Some(source_entity) = source else {
+      return false;
+    };
+    let Ok(source_position) = targets.positions.get(source_entity) else {
+      return true;
+    };
+    if !worker.walk_to(&mut targets.commands, source_position) {
+      return false;
+    }
+
+    let wanted = (*amount).min(worker.cargo.0.free_space());
+    let taken = if let Ok(mut output) = targets.outputs.get_mut(source_entity) {
+      output.0.remove(item, wanted)
+    } else {
+      take_from(&mut targets.commands, source_entity, item, wanted, &mut targets.storages, &mut targets.floor.stacks)
+    };
+
+    worker.cargo.0.add(item, taken);
+    *amount = taken;
+    *picked_up = true;
+    return taken == 0;
+  }
+
+  let destination_position = match destination {
+    Dropoff::Building(entity) => targets.positions.get(entity).ok().copied(),
+    Dropoff::Tile(position) => Some(position),
+  };
+  let Some(destination_position) = destination_position else {
+    return true;
+  };
+  if !worker.walk_to(&mut targets.commands, &destination_position) {
+    return false;
+  }
+
+  let carried = (*amount).min(worker.cargo.0.count(item));
+  let delivered = match destination {
+    Dropoff::Building(entity) => {
+      if let Ok(mut storage) = targets.storages.get_mut(entity) {
+        storage.0.add(item, carried)
+      } else if let Ok(mut input) = targets.inputs.get_mut(entity) {
+        input.0.add(item, carried)
+      } else {
+        0
+      }
+    },
+    Dropoff::Tile(position) => {
+      targets.floor.drop_on_tile(&mut targets.commands, item, carried, position)
+    },
+  };
+
+  worker.cargo.0.remove(item, delivered);
+  true
+}
+
+/// Works a lab for one session and banks its research points
+fn work_research(worker: &Worker, targets: &mut WorkTargets, lab: Entity) -> bool {
+  let (Ok(lab_position), Ok(Lab(definition))) = (targets.positions.get(lab), targets.labs.get(lab)) else {
+    return true;
+  };
+
+  let ticks = definition.ticks.div_ceil(worker.work_rate);
+  let finished = worker.work_at(&mut targets.commands, lab_position, lab, ticks, true);
+  if finished {
+    targets.research.add_points(definition.points, &targets.archetypes);
+  }
+  finished
+}
+
+/// Sits at a charger until the battery is full
+fn work_charge(worker: &mut Worker, targets: &mut WorkTargets, charger: Entity) -> bool {
+  let (Ok(charger_position), Ok(Charger(definition))) = (targets.positions.get(charger), targets.chargers.get(charger)) else {
+    return true;
+  };
+
+  let ticks = (worker.energy.capacity - worker.energy.charge).div_ceil(definition.rate.max(1)).max(1);
+  let charged = worker.work_at(&mut targets.commands, charger_position, charger, ticks, true);
+  if charged {
+    worker.energy.charge = worker.energy.capacity;
+  }
+  charged
+}
+
+/// Tears a building down, spilling whatever it held onto the floor around it
+fn work_deconstruct(worker: &Worker, targets: &mut WorkTargets, target: Entity) -> bool {
+  let Ok(target_position) = targets.positions.get(target) else {
+    return true;
+  };
+
+  let ticks = DECONSTRUCT_TICKS.div_ceil(worker.work_rate);
+  let finished = worker.work_at(&mut targets.commands, target_position, target, ticks, false);
+  if finished {
+    let mut contents = Vec::new();
+    if let Ok(storage) = targets.storages.get(target) {
+      contents.extend(storage.0.items.clone());
+    }
+    if let Ok(input) = targets.inputs.get(target) {
+      contents.extend(input.0.items.clone());
+    }
+    if let Ok(output) = targets.outputs.get(target) {
+      contents.extend(output.0.items.clone());
+    }
+
+    let impassable_set: HashSet<Entity> = targets.impassable.iter().filter(|entity| *entity != target).collect();
+    for (item, count) in contents {
+      targets.floor.drop_near(&mut targets.commands, &impassable_set, &item, count, target_position);
+    }
+    debug!("Bot {:?} deconstructed {:?}", worker.entity, target);
+    targets.commands.entity(target).despawn();
+  }
+  finished
+}
+
+/// Turns a supplied blueprint into its building
+fn work_build(worker: &Worker, targets: &mut WorkTargets, site: Entity) -> bool {
+  let (Ok(site_position), Ok((blueprint, heading))) = (targets.positions.get(site), targets.blueprints.get(site)) else {
+    return true;
+  };
+
+  let definition = targets.archetypes.building(&blueprint.0);
+  let build_ticks = definition.map_or(0, |definition| definition.build_ticks);
+
+  // An impassable building would seal in whoever stands on the site, so the
+  // finished work waits for the tile to clear and nudges anyone idle off it
+  let done = worker.interaction.is_some_and(|interaction| interaction.completed);
+  if done && definition.is_some_and(|definition| definition.impassable) {
+    let occupants: Vec<(Entity, bool)> = targets.grid.residents(site_position).iter()
+      .filter_map(|entity| targets.occupants.get(*entity).ok().map(|moving| (*entity, moving)))
+      .collect();
+    if !occupants.is_empty() {
+      let impassable_set: HashSet<Entity> = targets.impassable.iter().collect();
+      let free = targets.grid.neighbors(site_position)
+        .find(|tile| targets.grid.residents(tile).iter().all(|entity| !impassable_set.contains(entity)));
+      if let Some(tile) = free {
+        for (occupant, moving) in occupants {
+          if occupant == worker.entity || !moving {
+            targets.commands.entity(occupant).insert(Path { target: tile, path: vec![tile] });
+          }
+        }
+      }
+      return false;
+    }
+  }
+
+  let built = worker.work_at(&mut targets.commands, site_position, site, build_ticks.div_ceil(worker.work_rate), false);
+  if built {
+    // The delivered cost is used up along with the site
+    debug!("Bot {:?} built {} at ({}, {})", worker.entity, blueprint.0, site_position.x, site_position.y);
+    targets.commands.entity(site).despawn();
+    if let Some(entity) = spawn_building(&mut targets.commands, &targets.archetypes, &blueprint.0, site_position.x, site_position.y) {
+      if let Some(heading) = heading {
+        targets.commands.entity(entity).insert(*heading);
+      }
+    }
+  }
+  built
+}
+
+/// Restores a damaged building or bot to full health, getting a broken bot going again
+fn work_repair(worker: &Worker, targets: &mut WorkTargets, target: Entity, bot_positions: &HashMap<Entity, Position>) -> bool {
+  let target_position = targets.positions.get(target).ok().or_else(|| bot_positions.get(&target)).copied();
+  let Some(target_position) = target_position.filter(|_| targets.healths.contains(target)) else {
+    return true;
+  };
+
+  let repaired = worker.work_at(&mut targets.commands, &target_position, target, REPAIR_TICKS.div_ceil(worker.work_rate), false);
+  if repaired {
+    if let Ok(mut health) = targets.healths.get_mut(target) {
+      health.repair();
+    }
+    targets.commands.entity(target).remove::<Broken>();
+  }
+  repaired
+}
+
+/// Fetches a module item, then has it installed at the workshop
+fn work_install(
+  worker: &mut Worker,
+  targets: &mut WorkTargets,
+  source: Entity,
+  workshop: Entity,
+  module: &str,
+  picked_up: &mut bool,
+) -> bool {
+  if !*picked_up {
+    let Ok(source_position) = targets.positions.get(source) else {
+      return true;
+    };
+    if !worker.walk_to(&mut targets.commands, source_position) {
+      return false;
+    }
+
+    let taken = take_from(&mut targets.commands, source, module, 1, &mut targets.storages, &mut targets.floor.stacks);
+    worker.cargo.0.add(module, taken);
+    *picked_up = true;
+    return taken == 0;
+  }
+
+  let (Ok(workshop_position), Some(definition)) = (targets.positions.get(workshop), targets.archetypes.module(module)) else {
+    return true;
+  };
+
+  let installed = worker.work_at(&mut targets.commands, workshop_position, workshop, definition.install_ticks, true);
+  if installed && worker.cargo.0.remove(module, 1) > 0 {
+    debug!("Bot {:?} installed {} in its {:?} slot", worker.entity, module, definition.slot);
+    worker.modules.install(definition.slot, module.to_string());
+  }
+  installed
+}
+
+/// Takes up to `wanted` of `item` out of a storage building or a loose item,
+/// despawning the loose item once it is used up
+fn take_from(
   commands: &mut Commands,
-  bot_entity: Entity,
-  bot_position: &Position,
-  scrap_position: &Position,
-  scrap_entity: Entity,
-  bot_path: Option<&Path>,
-  interaction: Option<&Interaction>
-) {
-  if distance(bot_position, scrap_position) <= 1.0 {
-    if interaction.is_none() {
-      info!("Bot {:?} is ready to mine scrap {:?}", bot_entity, scrap_entity);
-      commands.entity(bot_entity).insert(Interaction::new(bot_entity, scrap_entity, 50));
-    } else if interaction.unwrap().completed {
-      info!("Bot {:?} has completed interaction with scrap {:?}", bot_entity, scrap_entity);
-      commands.entity(bot_entity).remove::<Interaction>();
-      commands.entity(bot_entity).remove::<Path>();
-      commands.entity(scrap_entity).despawn();
-    }
-  } else if bot_path.is_none() {
-    commands.entity(bot_entity).insert(Path::new(*scrap_position));
-  }
-}
\ No newline at end of file
+  source: Entity,
+  item: &str,
+  wanted: u32,
+  storages: &mut Query<&mut Storage>,
+  items: &mut Query<&mut Item>,
+) -> u32 {
+  if let Ok(mut storage) = storages.get_mut(source) {
+    return storage.0.remove(item, wanted);
+  }
+
+  let Ok(mut loose) = items.get_mut(source) else {
+    return 0;
+  };
+  if loose.name != item {
+    return 0;
+  }
+
+  let taken = loose.count.min(wanted);
+  loose.count -= taken;
+  if loose.count == 0 {
+    commands.entity(source).despawn();
+  }
+  taken
+}
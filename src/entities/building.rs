@@ -0,0 +1,259 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, VecDeque};
+use crate::archetype::{Archetypes, ResearchDefinition};
+use crate::research::Research;
+use crate::interact::Interaction;
+use crate::inventory::{Inventory, InputBuffer, OutputBuffer};
+use crate::grid::{Obstacles, Position};
+use crate::pathfinding::find_passable_adjacent_tile;
+use crate::colony::{Alerts, AlertKind, Population};
+use crate::simulation::SimulationClock;
+use crate::spawn::spawn_bot;
+
+/// Most bots a single factory will hold in its queue
+pub const MAX_QUEUED_BOTS: usize = 5;
+/// Bot archetype queued from the keyboard
+const QUEUED_BOT: &str = "worker";
+
+#[derive(Component)]
+pub struct Building;
+
+/// A site where bots deliver a building's cost and then build it. Named by
+/// the building archetype it turns into.
+#[derive(Component)]
+pub struct Blueprint(pub String);
+
+impl Blueprint {
+  /// Whether everything the building costs has been delivered
+  pub fn is_supplied(&self, input: &Inventory, archetypes: &Archetypes) -> bool {
+    archetypes.building(&self.0)
+      .is_some_and(|building| building.cost.iter().all(|(item, count)| input.count(item) >= *count))
+  }
+}
+
+/// A building whose archetype research hasn't unlocked yet. It does no work.
+#[derive(Component)]
+pub struct Locked;
+
+/// A building bots work to generate research points
+#[derive(Component)]
+pub struct Lab(pub ResearchDefinition);
+
+/// A building where bots get modules installed
+#[derive(Component)]
+pub struct Workshop;
+
+/// A building that turns input items into output items by running recipes
+#[derive(Component)]
+pub struct Fabricator {
+  /// Recipe names in order of preference
+  pub recipes: Vec<String>,
+  pub active_recipe: Option<String>,
+}
+
+impl Fabricator {
+  pub fn new(recipes: Vec<String>) -> Self {
+    Self { recipes, active_recipe: None }
+  }
+}
+
+/// A building that assembles queued bots from the components in its input buffer
+#[derive(Component, Default)]
+pub struct BotFactory {
+  /// Bot archetypes waiting to be built, front first. The front one is the
+  /// one being built while the factory has an `Interaction`.
+  pub queue: VecDeque<String>,
+}
+
+/// The input sets a production building is working towards, in order of
+/// preference: a fabricator's recipes, the cost of a factory's next bot, or
+/// the cost of a blueprint's building.
+pub fn input_requirements<'a>(
+  fabricator: Option<&Fabricator>,
+  factory: Option<&BotFactory>,
+  blueprint: Option<&Blueprint>,
+  archetypes: &'a Archetypes,
+  research: &Research,
+) -> Vec<&'a BTreeMap<String, u32>> {
+  let mut requirements: Vec<&BTreeMap<String, u32>> = Vec::new();
+
+  if let Some(fabricator) = fabricator {
+    requirements.extend(fabricator.recipes.iter()
+      .filter(|name| research.recipe_unlocked(name, archetypes))
+      .filter_map(|name| archetypes.recipe(name))
+      .map(|recipe| &recipe.inputs));
+  }
+  if let Some(name) = factory.and_then(|factory| factory.queue.front()) {
+    requirements.extend(archetypes.bot(name).map(|bot| &bot.cost));
+  }
+  if let Some(blueprint) = blueprint {
+    requirements.extend(archetypes.building(&blueprint.0).map(|building| &building.cost));
+  }
+
+  requirements
+}
+
+/// How many of `item` the building would accept for any of its requirements
+pub fn wanted(requirements: &[&BTreeMap<String, u32>], item: &str, input: &Inventory) -> u32 {
+  requirements.iter()
+    .filter_map(|inputs| inputs.get(item))
+    .map(|needed| needed.saturating_sub(input.count(item)))
+    .max()
+    .unwrap_or(0)
+    .min(input.free_space())
+}
+
+/// The next item the building wants hauled in, and how many. Picks the first
+/// requirement whose missing inputs are all available in `stock`.
+pub fn demand(requirements: &[&BTreeMap<String, u32>], input: &Inventory, stock: &BTreeMap<String, u32>) -> Option<(String, u32)> {
+  for inputs in requirements {
+    let missing: Vec<(&String, u32)> = inputs.iter()
+      .map(|(item, needed)| (item, needed.saturating_sub(input.count(item))))
+      .filter(|(_, missing)| *missing > 0)
+      .collect();
+
+    if missing.is_empty() {
+      return None;
+    }
+
+    let available = missing.iter()
+      .all(|(item, missing)| stock.get(*item).copied().unwrap_or(0) >= *missing);
+
+    if available {
+      let (item, amount) = missing[0];
+      return Some((item.clone(), amount.min(input.free_space())))
+        .filter(|(_, amount)| *amount > 0);
+    }
+  }
+
+  None
+}
+
+type FabricatorParts = (Entity, &'static mut Fabricator, &'static mut InputBuffer, &'static mut OutputBuffer, Option<&'static Interaction>);
+
+/// Starts a recipe as a building-side interaction once its inputs are in the
+/// buffer, and moves the products to the output buffer when it completes.
+pub fn run_fabricators(
+  mut commands: Commands,
+  archetypes: Res<Archetypes>,
+  research: Res<Research>,
+  mut fabricators: Query<FabricatorParts, Without<Locked>>,
+) {
+  for (entity, mut fabricator, mut input, mut output, interaction) in fabricators.iter_mut() {
+    if let Some(interaction) = interaction {
+      if !interaction.completed {
+        continue;
+      }
+
+      if let Some(recipe) = fabricator.active_recipe.take().and_then(|name| archetypes.recipe(&name)) {
+        for (item, amount) in &recipe.outputs {
+          output.0.add(item, *amount);
+        }
+      }
+      commands.entity(entity).remove::<Interaction>();
+      continue;
+    }
+
+    let ready = fabricator.recipes.iter().find(|name| {
+      research.recipe_unlocked(name, &archetypes) && archetypes.recipe(name).is_some_and(|recipe| {
+        recipe.inputs.iter().all(|(item, amount)| input.0.count(item) >= *amount)
+          && output.0.free_space() >= recipe.outputs.values().sum::<u32>()
+      })
+    }).cloned();
+
+    let Some(recipe_name) = ready else {
+      continue;
+    };
+    let Some(recipe) = archetypes.recipe(&recipe_name) else {
+      continue;
+    };
+
+    for (item, amount) in &recipe.inputs {
+      input.0.remove(item, *amount);
+    }
+    debug!("Fabricator {:?} started {}", entity, recipe_name);
+    commands.entity(entity).insert(Interaction::new(entity, recipe.ticks).powered_by(entity));
+    fabricator.active_recipe = Some(recipe_name);
+  }
+}
+
+/// Queues a bot at the factory with the shortest queue when B is pressed
+pub fn queue_bots(
+  keys: Res<ButtonInput<KeyCode>>,
+  mut factories: Query<&mut BotFactory, Without<Locked>>,
+) {
+  if !keys.just_pressed(KeyCode::KeyB) {
+    return;
+  }
+
+  let Some(mut factory) = factories.iter_mut().min_by_key(|factory| factory.queue.len()) else {
+    info!("There is no bot factory to queue a bot at");
+    return;
+  };
+
+  if factory.queue.len() >= MAX_QUEUED_BOTS {
+    info!("Every bot factory queue is full");
+    return;
+  }
+  factory.queue.push_back(QUEUED_BOT.to_string());
+}
+
+type FactoryParts = (Entity, &'static Position, &'static mut BotFactory, &'static mut InputBuffer, Option<&'static Interaction>);
+
+/// Starts the next queued bot once its cost is in the input buffer and the
+/// population cap allows it, and places the finished bot on a free tile next
+/// to the factory.
+pub fn run_bot_factories(
+  mut commands: Commands,
+  archetypes: Res<Archetypes>,
+  obstacles: Obstacles,
+  population: Res<Population>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
+  mut factories: Query<FactoryParts, Without<Locked>>,
+) {
+  let impassable_set = obstacles.impassable();
+  // Bots under construction count towards the cap so factories can't overshoot it
+  let mut planned = population.bots + factories.iter()
+    .filter(|(_, _, _, _, interaction)| interaction.is_some())
+    .count() as u32;
+
+  for (entity, position, mut factory, mut input, interaction) in factories.iter_mut() {
+    if let Some(interaction) = interaction {
+      if !interaction.completed {
+        continue;
+      }
+
+      let kind = AlertKind::BlockedFactory(entity);
+      let Some(tile) = find_passable_adjacent_tile(position, &obstacles.grid, &impassable_set) else {
+        alerts.raise(kind, format!("Bot factory at ({}, {}) has no free tile", position.x, position.y), clock.tick);
+        continue;
+      };
+      alerts.clear(&kind);
+
+      if let Some(name) = factory.queue.pop_front() {
+        debug!("Bot factory {:?} finished a {} at {:?}", entity, name, tile);
+        spawn_bot(&mut commands, &archetypes, &name, tile.x, tile.y);
+      }
+      commands.entity(entity).remove::<Interaction>();
+      continue;
+    }
+
+    if planned >= population.cap {
+      continue;
+    }
+
+    let Some(definition) = factory.queue.front().and_then(|name| archetypes.bot(name)) else {
+      continue;
+    };
+    if !definition.cost.iter().all(|(item, amount)| input.0.count(item) >= *amount) {
+      continue;
+    }
+
+    for (item, amount) in &definition.cost {
+      input.0.remove(item, *amount);
+    }
+    commands.entity(entity).insert(Interaction::new(entity, definition.build_ticks.max(1)).powered_by(entity));
+    planned += 1;
+  }
+}
@@ -1,12 +1,39 @@
 use bevy::prelude::*;
+use crate::archetype::ResourceDefinition;
 
 #[derive(Component)]
 pub struct Scrap {
-    pub size: u32
+    /// Amount of resource the colony gains from mining it
+    pub size: u32,
+    /// Ticks of work needed to mine this scrap at a work rate of 1, before hardness
+    pub mining_ticks: u32,
+    /// Multiplies the ticks each mining session takes
+    pub hardness: u32,
+    /// Lowest tool tier a bot needs to mine it
+    pub tier: u32,
+    /// Most one mining session breaks off, or as much as the bot can carry when `None`
+    pub yield_per_session: Option<u32>,
 }
 
 impl Scrap {
-    pub fn new(size: u32) -> Self {
-        Self { size }
+    pub fn new(definition: &ResourceDefinition) -> Self {
+        Self {
+            size: definition.size,
+            mining_ticks: definition.mining_ticks,
+            hardness: definition.hardness.max(1),
+            tier: definition.tier,
+            yield_per_session: definition.yield_per_session,
+        }
+    }
+
+    /// Picks up an edited definition without refilling what has been mined,
+    /// only cutting the size down if the new one is smaller
+    pub fn redefine(&mut self, definition: &ResourceDefinition) {
+        *self = Self { size: self.size.min(definition.size), ..Self::new(definition) };
+    }
+
+    /// Ticks one mining session takes at `work_rate`
+    pub fn session_ticks(&self, work_rate: u32) -> u32 {
+        (self.mining_ticks * self.hardness).div_ceil(work_rate)
     }
 }
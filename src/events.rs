@@ -0,0 +1,157 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use rand::Rng;
+use std::collections::HashSet;
+use crate::grid::{Grid, Impassable, Obstacles, Position};
+use crate::archetype::Archetypes;
+use crate::renderable::Renderable;
+use crate::designation::Designation;
+use crate::reservation::{ReservationSystem, ReservationKey};
+use crate::energy::Energy;
+use crate::colony::{Alerts, AlertKind};
+use crate::simulation::{SimulationClock, SimulationRng};
+use crate::spawn::spawn_resource;
+
+/// Fewest and most ticks between two events
+const EVENT_INTERVAL_TICKS: std::ops::Range<u64> = 900..2400;
+/// Resource archetype meteors leave behind
+const METEOR_RESOURCE: &str = "scrap";
+/// Share of their charge bots lose to a power surge, in percent
+const SURGE_DRAIN_PERCENT: u32 = 50;
+
+/// Impassable debris from an event. It is spawned designated for
+/// deconstruction, so bots clear it like a building.
+#[derive(Component)]
+pub struct Rubble;
+
+#[derive(Clone, Copy, Debug)]
+enum WorldEvent {
+  MeteorShower,
+  PowerSurge,
+  CaveIn,
+}
+
+/// When the next event fires. Timings and events are drawn from the
+/// simulation's seeded generator, so every run of a seed sees the same ones.
+#[derive(Resource)]
+pub struct EventScheduler {
+  next_tick: u64,
+}
+
+impl FromWorld for EventScheduler {
+  fn from_world(world: &mut World) -> Self {
+    let mut rng = world.get_resource_or_insert_with(SimulationRng::default);
+    Self { next_tick: rng.0.gen_range(EVENT_INTERVAL_TICKS) }
+  }
+}
+
+fn spawn_rubble(commands: &mut Commands, position: Position) {
+  commands.spawn((
+    Renderable::new(0.35, 0.3, 0.25),
+    position,
+    Rubble,
+    Impassable {},
+    Designation::Deconstruct,
+  ));
+}
+
+/// Tiles nothing stands on and no bot is bringing items to
+fn free_tiles(grid: &Grid, reservations: &ReservationSystem) -> Vec<Position> {
+  grid.tiles.iter().flatten()
+    .filter(|tile| tile.residents.is_empty())
+    .map(|tile| tile.position)
+    .filter(|position| !reservations.is_reserved(&ReservationKey::Tile(*position)))
+    .collect()
+}
+
+/// What events can strike
+#[derive(SystemParam)]
+pub struct EventTargets<'w, 's> {
+  obstacles: Obstacles<'w, 's>,
+  archetypes: Res<'w, Archetypes>,
+  reservations: Res<'w, ReservationSystem>,
+  energies: Query<'w, 's, &'static mut Energy>,
+}
+
+pub fn run_events(
+  mut commands: Commands,
+  mut scheduler: ResMut<EventScheduler>,
+  mut rng: ResMut<SimulationRng>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
+  mut targets: EventTargets,
+) {
+  if clock.tick < scheduler.next_tick {
+    return;
+  }
+  let rng = &mut rng.0;
+  scheduler.next_tick = clock.tick + rng.gen_range(EVENT_INTERVAL_TICKS);
+
+  let event = match rng.gen_range(0..3) {
+    0 => WorldEvent::MeteorShower,
+    1 => WorldEvent::PowerSurge,
+    _ => WorldEvent::CaveIn,
+  };
+  let grid = &targets.obstacles.grid;
+  let impassable_set = targets.obstacles.impassable();
+  // Tiles taken by this event, since its spawns only reach the grid next frame
+  let mut free = free_tiles(grid, &targets.reservations);
+  // Tiles this event walled up, which must not seal anything off either
+  let mut sealed: HashSet<Position> = HashSet::new();
+  let splits = |position: &Position, sealed: &HashSet<Position>| grid.would_split(position, &impassable_set, sealed);
+
+  let message = match event {
+    WorldEvent::MeteorShower => {
+      let impassable = targets.archetypes.resource(METEOR_RESOURCE).is_some_and(|definition| definition.impassable);
+      let mut landed = 0;
+      for _ in 0..rng.gen_range(1..=3) {
+        let sites: Vec<usize> = (0..free.len())
+          .filter(|index| !impassable || !splits(&free[*index], &sealed))
+          .collect();
+        if sites.is_empty() {
+          break;
+        }
+        let impact = free.swap_remove(sites[rng.gen_range(0..sites.len())]);
+        spawn_resource(&mut commands, &targets.archetypes, METEOR_RESOURCE, impact.x, impact.y);
+        if impassable {
+          sealed.insert(impact);
+        }
+        landed += 1;
+
+        // Each impact throws up a crater on a free tile or two beside it
+        let mut beside: Vec<Position> = grid.neighbors(&impact).filter(|neighbor| free.contains(neighbor)).collect();
+        for _ in 0..rng.gen_range(1..=2) {
+          beside.retain(|tile| !splits(tile, &sealed));
+          if beside.is_empty() {
+            break;
+          }
+          let crater = beside.swap_remove(rng.gen_range(0..beside.len()));
+          free.retain(|position| *position != crater);
+          spawn_rubble(&mut commands, crater);
+          sealed.insert(crater);
+        }
+      }
+      format!("Meteor shower: {} new scrap deposits", landed)
+    },
+    WorldEvent::PowerSurge => {
+      for mut energy in targets.energies.iter_mut() {
+        energy.charge -= energy.charge * SURGE_DRAIN_PERCENT / 100;
+      }
+      format!("Power surge drained {}% of every bot's charge", SURGE_DRAIN_PERCENT)
+    },
+    WorldEvent::CaveIn => {
+      let center = Position::new(rng.gen_range(0..grid.width), rng.gen_range(0..grid.height));
+      let area = free.iter().filter(|position| position.x.abs_diff(center.x) <= 1 && position.y.abs_diff(center.y) <= 1);
+      for position in area {
+        if !splits(position, &sealed) {
+          spawn_rubble(&mut commands, *position);
+          sealed.insert(*position);
+        }
+      }
+      format!("Cave-in at ({}, {}): {} tiles of rubble to clear", center.x, center.y, sealed.len())
+    },
+  };
+
+  info!("{:?}: {}", event, message);
+  alerts.raise(AlertKind::WorldEvent(clock.tick), message, clock.tick);
+}
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use crate::grid::{Grid, Impassable, Position, TileSprite, GRID_WIDTH, GRID_HEIGHT};
+use crate::archetype::{Archetype, Archetypes};
+use crate::entities::bot::Bot;
+use crate::entities::building::Building;
+use crate::health::Broken;
+use crate::hostiles::Hostile;
+use crate::renderable::Renderable;
+
+/// How far buildings see, in tiles
+const BUILDING_SIGHT: u32 = 3;
+/// Brightness of explored tiles no one currently sees
+const EXPLORED_BRIGHTNESS: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TileVisibility {
+  /// Never seen. Hidden, and bots don't know what is there.
+  #[default]
+  Unexplored,
+  /// Seen before but not right now
+  Explored,
+  /// In sight of a bot or building
+  Visible,
+}
+
+/// What the colony knows of each tile, indexed like `Grid::tiles`
+#[derive(Resource)]
+pub struct FogOfWar {
+  tiles: Vec<TileVisibility>,
+}
+
+impl Default for FogOfWar {
+  fn default() -> Self {
+    Self { tiles: vec![TileVisibility::Unexplored; (GRID_WIDTH * GRID_HEIGHT) as usize] }
+  }
+}
+
+impl FogOfWar {
+  pub fn visibility(&self, position: &Position) -> TileVisibility {
+    self.tiles.get(position.index()).copied().unwrap_or_default()
+  }
+
+  pub fn is_explored(&self, position: &Position) -> bool {
+    self.visibility(position) != TileVisibility::Unexplored
+  }
+
+  /// An explored tile next to an unexplored one, where exploring carries on from
+  pub fn is_frontier(&self, position: &Position, grid: &Grid) -> bool {
+    self.is_explored(position) && grid.neighbors(position).any(|neighbor| !self.is_explored(&neighbor))
+  }
+}
+
+/// Whether nothing impassable stands on the tiles strictly between `from` and `to`
+fn line_of_sight(from: &Position, to: &Position, grid: &Grid, impassable: &HashSet<Entity>) -> bool {
+  // Bresenham's line, skipping both ends
+  let (mut x, mut y) = (from.x as i32, from.y as i32);
+  let (end_x, end_y) = (to.x as i32, to.y as i32);
+  let (dx, dy) = ((end_x - x).abs(), -(end_y - y).abs());
+  let (step_x, step_y) = ((end_x - x).signum(), (end_y - y).signum());
+  let mut error = dx + dy;
+
+  loop {
+    let doubled = 2 * error;
+    if doubled >= dy {
+      error += dy;
+      x += step_x;
+    }
+    if doubled <= dx {
+      error += dx;
+      y += step_y;
+    }
+    if (x, y) == (end_x, end_y) {
+      return true;
+    }
+    if !grid.is_passable(&Position::new(x as u32, y as u32), impassable) {
+      return false;
+    }
+  }
+}
+
+type Spotter = (With<Bot>, Without<Broken>);
+
+/// Marks tiles in sight of bots and buildings visible, and what they saw before explored
+pub fn update_visibility(
+  mut fog: ResMut<FogOfWar>,
+  grid: Res<Grid>,
+  archetypes: Res<Archetypes>,
+  bots: Query<(&Position, &Archetype), Spotter>,
+  buildings: Query<&Position, With<Building>>,
+  impassable: Query<Entity, With<Impassable>>,
+) {
+  let impassable_set: HashSet<Entity> = impassable.iter().collect();
+  let viewers = bots.iter()
+    .map(|(position, archetype)| (position, archetypes.bot(&archetype.0).map_or(0, |bot| bot.sight_radius)))
+    .chain(buildings.iter().map(|position| (position, BUILDING_SIGHT)));
+
+  let mut tiles: Vec<TileVisibility> = fog.tiles.iter()
+    .map(|visibility| match visibility {
+      TileVisibility::Visible => TileVisibility::Explored,
+      other => *other,
+    })
+    .collect();
+
+  for (viewer, radius) in viewers {
+    let radius_squared = (radius * radius) as i64;
+    let range = |center: u32, size: u32| center.saturating_sub(radius)..=(center + radius).min(size - 1);
+    for x in range(viewer.x, grid.width) {
+      for y in range(viewer.y, grid.height) {
+        let tile = Position::new(x, y);
+        let (dx, dy) = (x as i64 - viewer.x as i64, y as i64 - viewer.y as i64);
+        if dx * dx + dy * dy <= radius_squared && line_of_sight(viewer, &tile, &grid, &impassable_set) {
+          tiles[tile.index()] = TileVisibility::Visible;
+        }
+      }
+    }
+  }
+
+  if fog.tiles != tiles {
+    fog.tiles = tiles;
+  }
+}
+
+/// Blacks out unexplored tiles and dims explored ones out of sight
+pub fn shade_tiles(
+  fog: Res<FogOfWar>,
+  grid: Res<Grid>,
+  mut tiles: Query<(&TileSprite, &mut Sprite, Has<TextureAtlas>)>,
+  atlased: Query<(), Added<TextureAtlas>>,
+) {
+  if !fog.is_changed() && atlased.is_empty() {
+    return;
+  }
+
+  for (tile, mut sprite, textured) in tiles.iter_mut() {
+    // Atlas tiles are drawn untinted, the rest in their terrain's color
+    let base = match grid.terrain_at(&tile.position) {
+      Some(terrain) if !textured => terrain.color(),
+      _ => Color::WHITE,
+    };
+    let brightness = match fog.visibility(&tile.position) {
+      TileVisibility::Unexplored => 0.0,
+      TileVisibility::Explored => EXPLORED_BRIGHTNESS,
+      TileVisibility::Visible => 1.0,
+    };
+    let linear = base.to_linear();
+    sprite.color = Color::linear_rgb(linear.red * brightness, linear.green * brightness, linear.blue * brightness);
+  }
+}
+
+/// Hides what stands on unexplored tiles, and hostiles no one can see
+pub fn hide_unseen(
+  fog: Res<FogOfWar>,
+  renderables: Query<(&Renderable, &Position, Has<Hostile>)>,
+  mut visibilities: Query<&mut Visibility>,
+) {
+  for (renderable, position, hostile) in renderables.iter() {
+    let Some(sprite) = renderable.sprite_entity() else {
+      continue;
+    };
+    let Ok(mut visibility) = visibilities.get_mut(sprite) else {
+      continue;
+    };
+
+    let seen = match fog.visibility(position) {
+      TileVisibility::Visible => true,
+      TileVisibility::Explored => !hostile,
+      TileVisibility::Unexplored => false,
+    };
+    let wanted = if seen { Visibility::Inherited } else { Visibility::Hidden };
+    if *visibility != wanted {
+      *visibility = wanted;
+    }
+  }
+}
@@ -1,4 +1,6 @@
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy::ecs::system::SystemParam;
 use std::collections::HashSet;
 
 pub const GRID_WIDTH: u32 = 20;
@@ -34,21 +36,44 @@ impl Resident {
   }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Terrain {
+  Floor,
+  Plating,
+}
+
+impl Terrain {
+  pub fn color(&self) -> Color {
+    match self {
+      Terrain::Floor => Color::srgb(0.2, 0.2, 0.2),
+      Terrain::Plating => Color::srgb(0.26, 0.26, 0.28),
+    }
+  }
+}
+
 #[derive(Clone)]
 pub struct Tile {
   pub position: Position,
   pub residents: Vec<Entity>,
+  pub terrain: Terrain,
 }
 
 impl Tile {
-  pub fn new(x: u32, y: u32) -> Self {
+  pub fn new(x: u32, y: u32, terrain: Terrain) -> Self {
     Self {
       position: Position::new(x, y), 
-      residents: Vec::new()
+      residents: Vec::new(),
+      terrain,
     }
   }
 }
 
+/// The sprite drawn for a grid tile
+#[derive(Component)]
+pub struct TileSprite {
+  pub position: Position,
+}
+
 #[derive(Component)]
 pub struct Impassable { }
 
@@ -66,6 +91,8 @@ impl Grid {
     Self { width, height, tile_size, tiles: vec![None; capacity] }
   }
 
+  /// Entities on the tiles reachable from `position`, tile by tile outward,
+  /// so nearer entities always come before further ones
   pub fn flood_search(&self, position: &Position, impassable_entities: &HashSet<Entity>) -> Vec<Entity> {
     use std::collections::VecDeque;
 
@@ -73,7 +100,7 @@ impl Grid {
     let mut visited = HashSet::new();
     let mut results = Vec::new();
 
-    queue.push_back(position.clone());
+    queue.push_back(*position);
     visited.insert(position.index());
 
     while let Some(current_pos) = queue.pop_front() {
@@ -111,17 +138,199 @@ impl Grid {
     results
   }
 
+  /// In-bounds orthogonal neighbors of a tile
+  pub fn neighbors(&self, position: &Position) -> impl Iterator<Item = Position> {
+    let (width, height) = (self.width, self.height);
+    [
+      (position.x.wrapping_sub(1), position.y),
+      (position.x + 1, position.y),
+      (position.x, position.y.wrapping_sub(1)),
+      (position.x, position.y + 1),
+    ]
+      .into_iter()
+      .filter(move |&(nx, ny)| nx < width && ny < height)
+      .map(|(nx, ny)| Position::new(nx, ny))
+  }
+
+  pub fn is_passable(&self, position: &Position, impassable_entities: &HashSet<Entity>) -> bool {
+    match self.tiles.get(position.index()) {
+      Some(Some(tile)) => !tile.residents.iter().any(|entity| impassable_entities.contains(entity)),
+      _ => false,
+    }
+  }
+
+  /// Labels every passable tile with the id of the connected region it belongs
+  /// to, indexed like `tiles`. Impassable tiles are `None`.
+  pub fn connected_regions(&self, impassable_entities: &HashSet<Entity>) -> Vec<Option<usize>> {
+    use std::collections::VecDeque;
+
+    let mut regions = vec![None; self.tiles.len()];
+    let mut next_region = 0;
+
+    for tile in self.tiles.iter().flatten() {
+      let start = tile.position;
+      if regions[start.index()].is_some() || !self.is_passable(&start, impassable_entities) {
+        continue;
+      }
+
+      let mut queue = VecDeque::from([start]);
+      regions[start.index()] = Some(next_region);
+
+      while let Some(current) = queue.pop_front() {
+        for neighbor in self.neighbors(&current) {
+          if regions[neighbor.index()].is_none() && self.is_passable(&neighbor, impassable_entities) {
+            regions[neighbor.index()] = Some(next_region);
+            queue.push_back(neighbor);
+          }
+        }
+      }
+
+      next_region += 1;
+    }
+
+    regions
+  }
+
+  /// Whether blocking `position` would cut its passable neighbors off from
+  /// each other. Tiles in `blocked` count as impassable too.
+  pub fn would_split(&self, position: &Position, impassable_entities: &HashSet<Entity>, blocked: &HashSet<Position>) -> bool {
+    use std::collections::VecDeque;
+
+    let passable = |tile: &Position| tile != position && !blocked.contains(tile) && self.is_passable(tile, impassable_entities);
+    let neighbors: Vec<Position> = self.neighbors(position).filter(|neighbor| passable(neighbor)).collect();
+    let Some(start) = neighbors.first() else {
+      return false;
+    };
+
+    let mut visited = vec![false; self.tiles.len()];
+    let mut queue = VecDeque::from([*start]);
+    visited[start.index()] = true;
+
+    while let Some(current) = queue.pop_front() {
+      for neighbor in self.neighbors(&current) {
+        if !visited[neighbor.index()] && passable(&neighbor) {
+          visited[neighbor.index()] = true;
+          queue.push_back(neighbor);
+        }
+      }
+    }
+
+    neighbors.iter().any(|neighbor| !visited[neighbor.index()])
+  }
+
+  pub fn terrain_at(&self, position: &Position) -> Option<Terrain> {
+    self.tiles.get(position.index())
+      .and_then(|tile| tile.as_ref())
+      .map(|tile| tile.terrain)
+  }
+
+  /// The tile under a world-space point, if it is on the grid
+  pub fn tile_at(&self, world: Vec2) -> Option<Position> {
+    let x = (world.x / self.tile_size + self.width as f32 / 2.0).floor();
+    let y = (world.y / self.tile_size + self.height as f32 / 2.0).floor();
+    if x < 0.0 || y < 0.0 || x >= self.width as f32 || y >= self.height as f32 {
+      return None;
+    }
+    Some(Position::new(x as u32, y as u32))
+  }
+
+  /// The passable tile matching `predicate` that is the fewest steps from `start`
+  pub fn nearest_tile(
+    &self,
+    start: &Position,
+    impassable_entities: &HashSet<Entity>,
+    mut predicate: impl FnMut(&Position) -> bool,
+  ) -> Option<Position> {
+    use std::collections::VecDeque;
+
+    let mut visited = vec![false; self.tiles.len()];
+    let mut queue = VecDeque::from([*start]);
+    visited[start.index()] = true;
+
+    while let Some(current) = queue.pop_front() {
+      if predicate(&current) {
+        return Some(current);
+      }
+
+      for neighbor in self.neighbors(&current) {
+        if !visited[neighbor.index()] && self.is_passable(&neighbor, impassable_entities) {
+          visited[neighbor.index()] = true;
+          queue.push_back(neighbor);
+        }
+      }
+    }
+
+    None
+  }
+
+  pub fn residents(&self, position: &Position) -> &[Entity] {
+    match self.tiles.get(position.index()) {
+      Some(Some(tile)) => &tile.residents,
+      _ => &[],
+    }
+  }
+
+  pub fn world_position(&self, position: &Position) -> Vec2 {
+    Vec2::new(
+      ((position.x as f32 - self.width as f32 / 2.0) + 0.5) * self.tile_size,
+      ((position.y as f32 - self.height as f32 / 2.0) + 0.5) * self.tile_size,
+    )
+  }
+
   fn index(&self, x: u32, y: u32) -> usize {
     (y * self.width + x) as usize
   }
 }
 
+/// The mouse cursor, mapped onto the grid through the 2D camera
+#[derive(SystemParam)]
+pub struct Cursor<'w, 's> {
+  grid: Res<'w, Grid>,
+  windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+  cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform)>,
+  ui: Query<'w, 's, &'static Interaction>,
+}
+
+impl Cursor<'_, '_> {
+  /// The tile under the mouse cursor, if any
+  pub fn tile(&self) -> Option<Position> {
+    let cursor = self.windows.get_single().ok()?.cursor_position()?;
+    let (camera, transform) = self.cameras.get_single().ok()?;
+    let world = camera.viewport_to_world_2d(transform, cursor)?;
+    self.grid.tile_at(world)
+  }
+
+  /// Whether the cursor is over a button or an open panel rather than the map
+  pub fn over_ui(&self) -> bool {
+    self.ui.iter().any(|interaction| *interaction != Interaction::None)
+  }
+}
+
+/// The grid and what stands in the way on it
+#[derive(SystemParam)]
+pub struct Obstacles<'w, 's> {
+  pub grid: Res<'w, Grid>,
+  impassable: Query<'w, 's, Entity, With<Impassable>>,
+}
+
+impl Obstacles<'_, '_> {
+  /// Every impassable entity, the way tile searches and pathfinding take them
+  pub fn impassable(&self) -> HashSet<Entity> {
+    self.impassable.iter().collect()
+  }
+}
+
 pub fn setup_grid(mut commands: Commands) {
   let mut grid = Grid::new(GRID_WIDTH, GRID_HEIGHT, TILE_SIZE);
 
-  for x in 0..GRID_WIDTH as u32 {
-    for y in 0..GRID_HEIGHT as u32 {
-      let tile = Tile::new(x, y);
+  for x in 0..GRID_WIDTH {
+    for y in 0..GRID_HEIGHT {
+      let terrain = if (8..12).contains(&x) && (8..12).contains(&y) {
+        Terrain::Plating
+      } else {
+        Terrain::Floor
+      };
+      let tile = Tile::new(x, y, terrain);
       let idx = grid.index(x, y);
       grid.tiles[idx] = Some(tile);
     }
@@ -132,21 +341,21 @@ pub fn setup_grid(mut commands: Commands) {
 }
 
 fn draw_tiles(commands: &mut Commands, grid: &Grid) {
-  for tile in &grid.tiles {
-    if let Some(tile) = tile {
-      let world_x = ((tile.position.x as f32 - grid.width as f32 / 2.0) + 0.5) * grid.tile_size;
-      let world_y = ((tile.position.y as f32 - grid.height as f32 / 2.0) + 0.5) * grid.tile_size;
+  for tile in grid.tiles.iter().flatten() {
+    let world = grid.world_position(&tile.position);
 
-      commands.spawn(SpriteBundle {
+    commands.spawn((
+      SpriteBundle {
         sprite: Sprite {
-          color: Color::srgb(0.2, 0.2, 0.2),
+          color: tile.terrain.color(),
           custom_size: Some(Vec2::new(grid.tile_size, grid.tile_size)),
           ..default()
         },
-        transform: Transform::from_xyz(world_x, world_y, 0.0),
+        transform: Transform::from_xyz(world.x, world.y, 0.0),
         ..default()
-      });
-    }
+      },
+      TileSprite { position: tile.position },
+    ));
   }
 }
 
@@ -155,11 +364,23 @@ pub fn add_new_positions_as_residents(mut commands: Commands, mut grid: ResMut<G
     let idx = position.index();
     if let Some(tile) = grid.tiles[idx].as_mut() {
       tile.residents.push(entity);
-      commands.entity(entity).insert(Resident::new(tile.position.clone()));
+      commands.entity(entity).insert(Resident::new(tile.position));
     }
   }
 }
 
+/// Drops despawned entities from the tiles they were on
+pub fn remove_despawned_residents(mut grid: ResMut<Grid>, mut removed: RemovedComponents<Resident>) {
+  let despawned: HashSet<Entity> = removed.read().collect();
+  if despawned.is_empty() {
+    return;
+  }
+
+  for tile in grid.tiles.iter_mut().flatten() {
+    tile.residents.retain(|entity| !despawned.contains(entity));
+  }
+}
+
 pub fn update_residents(mut commands: Commands, mut grid: ResMut<Grid>, query: Query<(Entity, &Position, &Resident), Changed<Position>>) {
   for (entity, position, resident) in query.iter() {
     let old_idx = resident.tile_position.index();
@@ -171,7 +392,71 @@ pub fn update_residents(mut commands: Commands, mut grid: ResMut<Grid>, query: Q
 
     if let Some(new_tile) = grid.tiles[new_idx].as_mut() {
       new_tile.residents.push(entity);
-      commands.entity(entity).insert(Resident::new(new_tile.position.clone()));
+      commands.entity(entity).insert(Resident::new(new_tile.position));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Bare floor covering the whole map
+  fn open_grid() -> Grid {
+    let mut grid = Grid::new(GRID_WIDTH, GRID_HEIGHT, TILE_SIZE);
+    for x in 0..GRID_WIDTH {
+      for y in 0..GRID_HEIGHT {
+        let index = grid.index(x, y);
+        grid.tiles[index] = Some(Tile::new(x, y, Terrain::Floor));
+      }
+    }
+    grid
+  }
+
+  /// Walls off column `x` top to bottom except at the rows in `gaps`
+  fn wall_column(grid: &mut Grid, x: u32, gaps: &[u32]) -> HashSet<Entity> {
+    let mut walls = HashSet::new();
+    for y in (0..GRID_HEIGHT).filter(|y| !gaps.contains(y)) {
+      let wall = Entity::from_raw(y);
+      let index = grid.index(x, y);
+      grid.tiles[index].as_mut().unwrap().residents.push(wall);
+      walls.insert(wall);
     }
+    walls
+  }
+
+  #[test]
+  fn open_floor_is_one_region() {
+    let regions = open_grid().connected_regions(&HashSet::new());
+    assert!(regions.iter().all(|region| *region == Some(0)));
+  }
+
+  #[test]
+  fn a_full_wall_splits_the_floor_in_two() {
+    let mut grid = open_grid();
+    let walls = wall_column(&mut grid, 5, &[]);
+    let regions = grid.connected_regions(&walls);
+
+    let region = |x, y| regions[Position::new(x, y).index()];
+    assert_eq!(region(5, 0), None);
+    assert_eq!(region(0, 0), region(4, GRID_HEIGHT - 1));
+    assert_eq!(region(6, 0), region(GRID_WIDTH - 1, GRID_HEIGHT - 1));
+    assert_ne!(region(0, 0), region(6, 0));
+  }
+
+  #[test]
+  fn filling_the_only_gap_in_a_wall_splits() {
+    let mut grid = open_grid();
+    let walls = wall_column(&mut grid, 5, &[10]);
+    assert!(grid.would_split(&Position::new(5, 10), &walls, &HashSet::new()));
+    assert!(!grid.would_split(&Position::new(8, 10), &walls, &HashSet::new()));
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn planned_blocks_count_as_walls() {
+    let mut grid = open_grid();
+    let walls = wall_column(&mut grid, 5, &[10, 11]);
+    assert!(!grid.would_split(&Position::new(5, 10), &walls, &HashSet::new()));
+    assert!(grid.would_split(&Position::new(5, 10), &walls, &HashSet::from([Position::new(5, 11)])));
+  }
+}
@@ -0,0 +1,367 @@
+use bevy::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+use crate::grid::{Grid, Position, Cursor};
+use crate::entities::bot::Bot;
+use crate::inventory::Storage;
+use crate::simulation::HOURS_PER_DAY;
+use crate::jobs::JobType;
+use crate::schedule::Schedule;
+use crate::impass::Door;
+use crate::hud::{text_style, BUTTON_IDLE, BUTTON_HOVERED};
+
+/// Group bots join when they are built
+pub const DEFAULT_GROUP: &str = "default";
+
+const BUTTON_SELECTED: Color = Color::srgb(0.2, 0.35, 0.5);
+
+/// Name of the group a bot belongs to
+#[derive(Component, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Group(pub String);
+
+impl Default for Group {
+  fn default() -> Self {
+    Self(DEFAULT_GROUP.to_string())
+  }
+}
+
+/// Where a group's bots may work, what they may do, and where they bring things home to
+#[derive(Clone, Debug)]
+pub struct GroupSettings {
+  /// Corners of the rectangle bots look for work in, or anywhere when `None`
+  pub area: Option<(Position, Position)>,
+  pub jobs: BTreeSet<JobType>,
+  /// Storage building bots deliver to first, wherever the area is, and wait at off shift
+  pub home: Option<Entity>,
+  pub schedule: Schedule,
+}
+
+impl Default for GroupSettings {
+  fn default() -> Self {
+    Self { area: None, jobs: JobType::ALL.into_iter().collect(), home: None, schedule: Schedule::default() }
+  }
+}
+
+impl GroupSettings {
+  pub fn covers(&self, position: &Position) -> bool {
+    self.area.is_none_or(|(min, max)| {
+      (min.x..=max.x).contains(&position.x) && (min.y..=max.y).contains(&position.y)
+    })
+  }
+
+  pub fn does(&self, job: JobType) -> bool {
+    self.jobs.contains(&job)
+  }
+}
+
+/// Every named group, and the one the groups screen and area tool act on
+#[derive(Resource)]
+pub struct BotGroups {
+  pub groups: BTreeMap<String, GroupSettings>,
+  pub selected: String,
+}
+
+impl Default for BotGroups {
+  fn default() -> Self {
+    Self {
+      groups: BTreeMap::from([(DEFAULT_GROUP.to_string(), GroupSettings::default())]),
+      selected: DEFAULT_GROUP.to_string(),
+    }
+  }
+}
+
+impl BotGroups {
+  pub fn get(&self, name: &str) -> Option<&GroupSettings> {
+    self.groups.get(name)
+  }
+
+  pub fn selected_mut(&mut self) -> Option<&mut GroupSettings> {
+    self.groups.get_mut(&self.selected)
+  }
+
+  /// Adds a group under the first free "group N" name and selects it
+  pub fn create(&mut self) {
+    let name = (2..)
+      .map(|number| format!("group {}", number))
+      .find(|name| !self.groups.contains_key(name))
+      .expect("group numbers never run out");
+    self.groups.insert(name.clone(), GroupSettings::default());
+    self.selected = name;
+  }
+}
+
+#[derive(Component)]
+pub struct GroupScreen;
+
+/// Holds the group buttons, rebuilt whenever the groups or their members change
+#[derive(Component)]
+pub struct GroupList;
+
+#[derive(Component, Clone, Debug)]
+pub enum GroupButton {
+  Select(String),
+  Job(JobType),
+  Hour(usize),
+  Home,
+  ClearArea,
+  Create,
+  Disband,
+}
+
+pub fn spawn_group_screen(mut commands: Commands) {
+  commands.spawn((
+    NodeBundle {
+      style: Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(36.0),
+        right: Val::Px(8.0),
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(4.0),
+        padding: UiRect::all(Val::Px(8.0)),
+        ..default()
+      },
+      background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+      visibility: Visibility::Hidden,
+      ..default()
+    },
+    GroupScreen,
+    Interaction::default(),
+  )).with_children(|screen| {
+    screen.spawn(TextBundle::from_section("Groups (K to close)", text_style(Color::WHITE)));
+    screen.spawn(TextBundle::from_section(
+      "A over a bot moves it to the selected group\nZ then drag sets the selected group's area",
+      text_style(Color::srgb(0.7, 0.7, 0.7)),
+    ));
+    screen.spawn((
+      NodeBundle {
+        style: Style {
+          flex_direction: FlexDirection::Column,
+          row_gap: Val::Px(2.0),
+          ..default()
+        },
+        ..default()
+      },
+      GroupList,
+    ));
+  });
+}
+
+pub fn toggle_group_screen(
+  keys: Res<ButtonInput<KeyCode>>,
+  mut screens: Query<&mut Visibility, With<GroupScreen>>,
+) {
+  if !keys.just_pressed(KeyCode::KeyK) {
+    return;
+  }
+
+  for mut visibility in screens.iter_mut() {
+    *visibility = match *visibility {
+      Visibility::Hidden => Visibility::Inherited,
+      _ => Visibility::Hidden,
+    };
+  }
+}
+
+/// Background of a button that isn't hovered, showing what it stands for
+fn button_color(button: &GroupButton, groups: &BotGroups) -> Color {
+  let settings = groups.get(&groups.selected);
+  match button {
+    GroupButton::Select(name) if *name == groups.selected => BUTTON_SELECTED,
+    GroupButton::Job(job) if settings.is_some_and(|settings| settings.does(*job)) => BUTTON_SELECTED,
+    GroupButton::Hour(hour) => settings.map_or(BUTTON_IDLE, |settings| settings.schedule.at(*hour).color()),
+    _ => BUTTON_IDLE,
+  }
+}
+
+fn spawn_button(list: &mut ChildBuilder, groups: &BotGroups, button: GroupButton, label: String) {
+  list.spawn((
+    ButtonBundle {
+      style: Style {
+        padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+        ..default()
+      },
+      background_color: button_color(&button, groups).into(),
+      ..default()
+    },
+    button,
+  )).with_children(|button| {
+    button.spawn(TextBundle::from_section(label, text_style(Color::WHITE)));
+  });
+}
+
+/// Lists each group with its member count, then the selected group's job
+/// toggles, timetable, home and area
+pub fn rebuild_group_list(
+  mut commands: Commands,
+  groups: Res<BotGroups>,
+  members: Query<&Group, With<Bot>>,
+  changed: Query<(), Changed<Group>>,
+  mut removed: RemovedComponents<Group>,
+  storages: Query<&Position, With<Storage>>,
+  lists: Query<Entity, With<GroupList>>,
+) {
+  let removed = removed.read().count() > 0;
+  if !groups.is_changed() && changed.is_empty() && !removed {
+    return;
+  }
+
+  let Some(settings) = groups.get(&groups.selected) else {
+    return;
+  };
+  let home = match settings.home.and_then(|home| storages.get(home).ok()) {
+    Some(position) => format!("Home: depot at ({}, {})", position.x, position.y),
+    None => "Home: none".to_string(),
+  };
+  let area = match settings.area {
+    Some((min, max)) => format!("Area: ({}, {}) to ({}, {}), click to clear", min.x, min.y, max.x, max.y),
+    None => "Area: anywhere".to_string(),
+  };
+
+  for list in lists.iter() {
+    commands.entity(list).despawn_descendants().with_children(|list| {
+      for name in groups.groups.keys() {
+        let count = members.iter().filter(|group| group.0 == *name).count();
+        spawn_button(list, &groups, GroupButton::Select(name.clone()), format!("{} ({} bots)", name, count));
+      }
+      spawn_button(list, &groups, GroupButton::Create, "New group".to_string());
+
+      list.spawn(TextBundle::from_section(format!("Selected: {}", groups.selected), text_style(Color::WHITE)));
+      for job in JobType::ALL {
+        let state = if settings.does(job) { "on" } else { "off" };
+        spawn_button(list, &groups, GroupButton::Job(job), format!("{}: {}", job.label(), state));
+      }
+
+      list.spawn(TextBundle::from_section(
+        "Timetable from midnight (W work, C charge, I idle)",
+        text_style(Color::srgb(0.7, 0.7, 0.7)),
+      ));
+      list.spawn(NodeBundle {
+        style: Style {
+          column_gap: Val::Px(1.0),
+          ..default()
+        },
+        ..default()
+      }).with_children(|row| {
+        for hour in 0..HOURS_PER_DAY as usize {
+          spawn_button(row, &groups, GroupButton::Hour(hour), settings.schedule.at(hour).letter().to_string());
+        }
+      });
+
+      spawn_button(list, &groups, GroupButton::Home, home.clone());
+      spawn_button(list, &groups, GroupButton::ClearArea, area.clone());
+      if groups.selected != DEFAULT_GROUP {
+        spawn_button(list, &groups, GroupButton::Disband, "Disband".to_string());
+      }
+    });
+  }
+}
+
+/// Selecting a group, toggling its jobs, cycling an hour of its timetable or
+/// its home through the storage buildings, clearing its area, and adding or
+/// disbanding groups. Bots of a disbanded group go back to the default one,
+/// and doors locked for it unlock.
+pub fn handle_group_buttons(
+  mut groups: ResMut<BotGroups>,
+  storages: Query<Entity, With<Storage>>,
+  mut members: Query<&mut Group>,
+  mut doors: Query<&mut Door>,
+  mut buttons: Query<(&Interaction, &GroupButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+  for (interaction, button, mut background) in buttons.iter_mut() {
+    match interaction {
+      Interaction::Pressed => {},
+      Interaction::Hovered => {
+        *background = BUTTON_HOVERED.into();
+        continue;
+      },
+      Interaction::None => {
+        *background = button_color(button, &groups).into();
+        continue;
+      },
+    }
+
+    match button {
+      GroupButton::Select(name) => groups.selected = name.clone(),
+      GroupButton::Create => groups.create(),
+      GroupButton::Job(job) => {
+        if let Some(settings) = groups.selected_mut() {
+          if !settings.jobs.remove(job) {
+            settings.jobs.insert(*job);
+          }
+        }
+      },
+      GroupButton::Hour(hour) => {
+        if let Some(settings) = groups.selected_mut() {
+          settings.schedule.cycle(*hour);
+        }
+      },
+      GroupButton::Home => {
+        let mut depots: Vec<Entity> = storages.iter().collect();
+        depots.sort();
+        if let Some(settings) = groups.selected_mut() {
+          // Cycles none -> each storage building in turn -> none
+          settings.home = match settings.home {
+            None => depots.first().copied(),
+            Some(current) => depots.iter().skip_while(|depot| **depot != current).nth(1).copied(),
+          };
+        }
+      },
+      GroupButton::ClearArea => {
+        if let Some(settings) = groups.selected_mut() {
+          settings.area = None;
+        }
+      },
+      GroupButton::Disband => {
+        let name = groups.selected.clone();
+        groups.groups.remove(&name);
+        groups.selected = DEFAULT_GROUP.to_string();
+        for mut group in members.iter_mut().filter(|group| group.0 == name) {
+          *group = Group::default();
+        }
+        for mut door in doors.iter_mut().filter(|door| door.locked_for.contains(&name)) {
+          door.locked_for.remove(&name);
+        }
+      },
+    }
+  }
+}
+
+/// Press A over a bot to move it to the selected group
+pub fn assign_bots(
+  keys: Res<ButtonInput<KeyCode>>,
+  groups: Res<BotGroups>,
+  cursor: Cursor,
+  mut bots: Query<(&Position, &mut Group), With<Bot>>,
+) {
+  if !keys.just_pressed(KeyCode::KeyA) {
+    return;
+  }
+  let Some(tile) = cursor.tile() else {
+    return;
+  };
+
+  for (_, mut group) in bots.iter_mut().filter(|(position, _)| **position == tile) {
+    if group.0 != groups.selected {
+      info!("Moved a bot from {} to {}", group.0, groups.selected);
+      group.0 = groups.selected.clone();
+    }
+  }
+}
+
+/// Outlines the selected group's area while the groups screen is open
+pub fn draw_group_area(
+  grid: Res<Grid>,
+  groups: Res<BotGroups>,
+  screens: Query<&Visibility, With<GroupScreen>>,
+  mut gizmos: Gizmos,
+) {
+  if !screens.iter().any(|visibility| *visibility != Visibility::Hidden) {
+    return;
+  }
+  let Some((min, max)) = groups.get(&groups.selected).and_then(|settings| settings.area) else {
+    return;
+  };
+
+  let a = grid.world_position(&min);
+  let b = grid.world_position(&max);
+  gizmos.rect_2d((a + b) / 2.0, 0.0, b - a + Vec2::splat(grid.tile_size), Color::srgb(0.3, 0.8, 1.0));
+}
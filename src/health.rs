@@ -0,0 +1,163 @@
+use bevy::prelude::*;
+use std::collections::HashSet;
+use crate::grid::{Grid, Impassable, Position};
+use crate::archetype::Archetype;
+use crate::entities::bot::Bot;
+use crate::entities::building::Building;
+use crate::colony::{Alerts, AlertKind};
+use crate::interact::Interaction;
+use crate::items::Floor;
+use crate::pathfinding::Path;
+use crate::reservation::ReservationSystem;
+use crate::simulation::SimulationClock;
+
+/// Below this percentage of their health, bots and buildings get repaired
+pub const REPAIR_THRESHOLD_PERCENT: u32 = 50;
+/// Ticks of bot work to repair something back to full health
+pub const REPAIR_TICKS: u32 = 80;
+/// Bots lose a point of health every this many ticks just from age
+const AGE_WEAR_TICKS: u64 = 300;
+/// Scrap left on the floor by a destroyed building
+const SALVAGE_SCRAP: u32 = 10;
+
+#[derive(Component, Debug)]
+pub struct Health {
+  pub current: u32,
+  pub max: u32,
+}
+
+impl Health {
+  pub fn new(max: u32) -> Self {
+    let max = max.max(1);
+    Self { current: max, max }
+  }
+
+  pub fn damage(&mut self, amount: u32) {
+    self.current = self.current.saturating_sub(amount);
+  }
+
+  pub fn is_dead(&self) -> bool {
+    self.current == 0
+  }
+
+  pub fn needs_repair(&self) -> bool {
+    self.current * 100 < self.max * REPAIR_THRESHOLD_PERCENT
+  }
+
+  pub fn repair(&mut self) {
+    self.current = self.max;
+  }
+
+  /// Keeps the same fraction of health when the maximum changes
+  pub fn set_max(&mut self, max: u32) {
+    let max = max.max(1);
+    self.current = (self.current as u64 * max as u64 / self.max as u64) as u32;
+    self.max = max;
+  }
+}
+
+/// A bot whose health ran out. It sits where it stopped, doing nothing,
+/// until another bot repairs it.
+#[derive(Component)]
+pub struct Broken;
+
+/// Bots wear down over their lifetime
+pub fn wear_bots(clock: Res<SimulationClock>, mut bots: Query<&mut Health, (With<Bot>, Without<Broken>)>) {
+  if !clock.tick.is_multiple_of(AGE_WEAR_TICKS) {
+    return;
+  }
+
+  for mut health in bots.iter_mut() {
+    health.damage(1);
+  }
+}
+
+/// Bots whose health has run out break down and drop whatever job they had
+pub fn break_down_bots(
+  mut commands: Commands,
+  mut bots: Query<(Entity, &mut Bot, &Health), Without<Broken>>,
+  mut reservations: ResMut<ReservationSystem>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
+) {
+  for (entity, mut bot, health) in bots.iter_mut() {
+    if !health.is_dead() {
+      continue;
+    }
+
+    if let Some(job) = bot.job.take() {
+      job.release(&mut reservations);
+    }
+    commands.entity(entity).insert(Broken).remove::<(Path, Interaction)>();
+    alerts.raise(AlertKind::Destroyed(entity), format!("Bot {} broke down", entity.index()), clock.tick);
+  }
+}
+
+type Remains = (Entity, &'static Health, &'static Position, Option<&'static Archetype>, Has<Building>);
+
+/// Despawns buildings and hostiles whose health has run out. Buildings leave
+/// salvage scrap behind.
+pub fn despawn_dead(
+  mut commands: Commands,
+  dead: Query<Remains, Without<Bot>>,
+  mut floor: Floor,
+  impassable: Query<Entity, With<Impassable>>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
+) {
+  for (entity, health, position, archetype, is_building) in dead.iter() {
+    if !health.is_dead() {
+      continue;
+    }
+
+    if is_building {
+      let name = archetype.map_or("building", |archetype| archetype.0.as_str());
+      alerts.raise(AlertKind::Destroyed(entity), format!("A {} was destroyed", name), clock.tick);
+
+      let impassable_set: HashSet<Entity> = impassable.iter().filter(|impassable| *impassable != entity).collect();
+      floor.drop_near(&mut commands, &impassable_set, "scrap", SALVAGE_SCRAP, position);
+    }
+
+    debug!("{:?} was destroyed", entity);
+    commands.entity(entity).despawn();
+  }
+}
+
+/// Bars under anything that has taken damage
+pub fn draw_health_bars(
+  grid: Res<Grid>,
+  damaged: Query<(&Position, &Health)>,
+  mut gizmos: Gizmos,
+) {
+  let width = grid.tile_size * 0.8;
+
+  for (position, health) in damaged.iter().filter(|(_, health)| health.current < health.max) {
+    let world = grid.world_position(position);
+    let left = world + Vec2::new(-width / 2.0, -grid.tile_size * 0.45);
+    let fraction = health.current as f32 / health.max as f32;
+
+    gizmos.line_2d(left, left + Vec2::new(width, 0.0), Color::srgb(0.3, 0.0, 0.0));
+    gizmos.line_2d(left, left + Vec2::new(width * fraction, 0.0), Color::srgb(0.9, 0.2, 0.2));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn raising_the_maximum_keeps_the_share_of_health() {
+    let mut health = Health::new(100);
+    health.damage(40);
+    health.set_max(150);
+    assert_eq!((health.current, health.max), (90, 150));
+  }
+
+  #[test]
+  fn a_zero_maximum_leaves_a_sliver_of_health() {
+    let mut health = Health::new(0);
+    assert_eq!((health.current, health.max), (1, 1));
+    health.set_max(0);
+    assert!(!health.is_dead());
+  }
+}
@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+use crate::grid::{Grid, Impassable, Obstacles, Position};
+use crate::archetype::{Archetype, Archetypes, HostileDefinition, TurretDefinition};
+use crate::entities::bot::Bot;
+use crate::entities::building::{Building, Locked};
+use crate::health::{Broken, Health};
+use crate::impass::{self, Door};
+use crate::movement::MovementSpeed;
+use crate::pathfinding::{Path, distance};
+use crate::renderable::Renderable;
+use crate::colony::{Alerts, AlertKind};
+use crate::simulation::{SimulationClock, SimulationRng};
+
+/// Hostile archetype sent in waves
+const WAVE_HOSTILE: &str = "drone";
+/// Tick the first wave arrives on
+const FIRST_WAVE_TICK: u64 = 1800;
+/// Ticks between waves
+const WAVE_INTERVAL_TICKS: u64 = 1200;
+/// Ticks between attacks of armed bots
+const BOT_ATTACK_TICKS: u64 = 10;
+/// How far away an armed bot can hit a hostile, in tiles
+const BOT_ATTACK_RANGE: f32 = 1.5;
+
+/// A drone that hunts the colony's bots and buildings
+#[derive(Component, Debug)]
+pub struct Hostile {
+  pub damage: u32,
+  pub attack_ticks: u32,
+  cooldown: u32,
+  /// The bot or building it is going after
+  pub target: Option<Entity>,
+}
+
+impl Hostile {
+  pub fn new(definition: &HostileDefinition) -> Self {
+    Self { damage: definition.damage, attack_ticks: definition.attack_ticks, cooldown: 0, target: None }
+  }
+}
+
+/// A building that shoots hostiles in range
+#[derive(Component, Debug)]
+pub struct Turret {
+  pub definition: TurretDefinition,
+  cooldown: u32,
+  /// The hostile it is shooting at
+  pub target: Option<Entity>,
+}
+
+impl Turret {
+  pub fn new(definition: TurretDefinition) -> Self {
+    Self { definition, cooldown: 0, target: None }
+  }
+}
+
+/// Counts the waves sent so far. Each wave sends one more drone than the last.
+#[derive(Resource, Default)]
+pub struct HostileWaves {
+  pub sent: u32,
+}
+
+pub fn spawn_hostile(commands: &mut Commands, archetypes: &Archetypes, name: &str, position: Position) {
+  let Some(definition) = archetypes.hostile(name) else {
+    warn!("No hostile archetype named {:?}", name);
+    return;
+  };
+
+  let (r, g, b) = definition.color;
+  let renderable = Renderable::new(r, g, b).with_rotation();
+  commands.spawn((
+    match definition.sprite {
+      Some(sprite) => renderable.with_animations(sprite.animations()),
+      None => renderable,
+    },
+    position,
+    Hostile::new(definition),
+    Health::new(definition.health),
+    MovementSpeed::new(definition.ticks_per_tile, 0),
+    Archetype(name.to_string()),
+  ));
+}
+
+/// A random passable tile on the edge of the map
+fn edge_tile(grid: &Grid, impassable: &HashSet<Entity>, rng: &mut impl Rng) -> Option<Position> {
+  let edges: Vec<Position> = grid.tiles.iter().flatten()
+    .map(|tile| tile.position)
+    .filter(|position| position.x == 0 || position.y == 0 || position.x == grid.width - 1 || position.y == grid.height - 1)
+    .filter(|position| grid.is_passable(position, impassable))
+    .collect();
+  if edges.is_empty() {
+    return None;
+  }
+  Some(edges[rng.gen_range(0..edges.len())])
+}
+
+/// Sends a wave of drones in from the map edges every so often
+pub fn spawn_waves(
+  mut commands: Commands,
+  mut waves: ResMut<HostileWaves>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
+  obstacles: Obstacles,
+  archetypes: Res<Archetypes>,
+  mut rng: ResMut<SimulationRng>,
+) {
+  if clock.tick < FIRST_WAVE_TICK || !(clock.tick - FIRST_WAVE_TICK).is_multiple_of(WAVE_INTERVAL_TICKS) {
+    return;
+  }
+
+  let impassable_set = obstacles.impassable();
+  waves.sent += 1;
+  for _ in 0..waves.sent {
+    if let Some(position) = edge_tile(&obstacles.grid, &impassable_set, &mut rng.0) {
+      spawn_hostile(&mut commands, &archetypes, WAVE_HOSTILE, position);
+    }
+  }
+
+  info!("Sent hostile wave {}", waves.sent);
+  alerts.raise(AlertKind::HostileWave, format!("{} hostile drones incoming", waves.sent), clock.tick);
+}
+
+/// Anything drones attack
+type Prey = (Or<(With<Bot>, With<Building>)>, Without<Hostile>, Without<Broken>);
+
+/// Drones pick the nearest working bot or building they can reach, walk up to it and
+/// attack it. They can't open doors.
+pub fn hunt(
+  mut commands: Commands,
+  grid: Res<Grid>,
+  impassable: Query<Entity, With<Impassable>>,
+  doors: Query<(Entity, &Position, &Door)>,
+  mut hostiles: Query<(Entity, &mut Hostile, &Position, Option<&Path>)>,
+  mut prey: Query<(&Position, &mut Health), Prey>,
+) {
+  let impassable_set: HashSet<Entity> = impassable.iter().collect();
+  let blocked = impass::blocked_for(None, &impassable_set, &doors);
+
+  for (entity, mut hostile, position, path) in hostiles.iter_mut() {
+    if hostile.target.is_some_and(|target| !prey.contains(target)) {
+      hostile.target = None;
+    }
+    if hostile.target.is_none() {
+      hostile.target = grid.flood_search(position, &blocked).into_iter().find(|entity| prey.contains(*entity));
+    }
+    let Some(target) = hostile.target else {
+      continue;
+    };
+    let Ok((target_position, mut health)) = prey.get_mut(target) else {
+      continue;
+    };
+
+    if distance(position, target_position) > 1.0 {
+      // Chase a moving target by re-pathing once it leaves the old destination
+      if path.is_none_or(|path| path.target != *target_position) {
+        commands.entity(entity).insert(Path::new(*target_position));
+      }
+      continue;
+    }
+
+    if path.is_some() {
+      commands.entity(entity).remove::<Path>();
+    }
+    if hostile.cooldown > 0 {
+      hostile.cooldown -= 1;
+    } else {
+      health.damage(hostile.damage);
+      hostile.cooldown = hostile.attack_ticks;
+    }
+  }
+}
+
+pub fn fire_turrets(
+  mut turrets: Query<(&mut Turret, &Position), Without<Locked>>,
+  mut hostiles: Query<(Entity, &Position, &mut Health), With<Hostile>>,
+) {
+  for (mut turret, position) in turrets.iter_mut() {
+    if turret.cooldown > 0 {
+      turret.cooldown -= 1;
+      continue;
+    }
+
+    let range = turret.definition.range;
+    let nearest = hostiles.iter()
+      .map(|(entity, hostile_position, _)| (entity, distance(position, hostile_position)))
+      .filter(|(_, distance)| *distance <= range)
+      .min_by(|a, b| a.1.total_cmp(&b.1))
+      .map(|(entity, _)| entity);
+
+    turret.target = nearest;
+    if let Some(Ok((_, _, mut health))) = nearest.map(|entity| hostiles.get_mut(entity)) {
+      health.damage(turret.definition.damage);
+      turret.cooldown = turret.definition.fire_ticks;
+    }
+  }
+}
+
+/// Bots with a weapon module hit the nearest hostile within reach
+pub fn armed_bots_attack(
+  clock: Res<SimulationClock>,
+  bots: Query<(&Bot, &Position), Without<Broken>>,
+  mut hostiles: Query<(Entity, &Position, &mut Health), With<Hostile>>,
+) {
+  if !clock.tick.is_multiple_of(BOT_ATTACK_TICKS) {
+    return;
+  }
+
+  for (bot, position) in bots.iter().filter(|(bot, _)| bot.attack > 0) {
+    let nearest = hostiles.iter()
+      .map(|(entity, hostile_position, _)| (entity, distance(position, hostile_position)))
+      .filter(|(_, distance)| *distance <= BOT_ATTACK_RANGE)
+      .min_by(|a, b| a.1.total_cmp(&b.1))
+      .map(|(entity, _)| entity);
+
+    if let Some(Ok((_, _, mut health))) = nearest.map(|entity| hostiles.get_mut(entity)) {
+      health.damage(bot.attack);
+    }
+  }
+}
+
+/// A line from each turret to what it is shooting at
+pub fn draw_shots(
+  grid: Res<Grid>,
+  turrets: Query<(&Turret, &Position)>,
+  positions: Query<&Position, With<Hostile>>,
+  mut gizmos: Gizmos,
+) {
+  for (turret, position) in turrets.iter() {
+    let Some(Ok(target)) = turret.target.map(|target| positions.get(target)) else {
+      continue;
+    };
+    gizmos.line_2d(grid.world_position(position), grid.world_position(target), Color::srgb(1.0, 0.9, 0.3));
+  }
+}
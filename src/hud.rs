@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use crate::colony::{Stockpile, Population, ColonyStats, Alerts, MINING_HISTORY_MINUTES};
+use crate::entities::bot::Bot;
+use crate::entities::building::BotFactory;
+use crate::entities::building::Blueprint;
+use crate::reservation::{ReservationSystem, ReservationKey};
+use crate::designation::Designation;
+use crate::jobs::JobKind;
+use crate::power::PowerGrid;
+
+const GRAPH_HEIGHT: f32 = 40.0;
+const GRAPH_BAR_WIDTH: f32 = 10.0;
+const MAX_ALERTS_SHOWN: usize = 4;
+/// Background of panel buttons, at rest and under the cursor
+pub const BUTTON_IDLE: Color = Color::srgb(0.15, 0.15, 0.2);
+pub const BUTTON_HOVERED: Color = Color::srgb(0.25, 0.25, 0.3);
+
+#[derive(Component)]
+pub struct ColonyText;
+
+#[derive(Component)]
+pub struct MiningGraphBar(usize);
+
+#[derive(Component)]
+pub struct AlertsText;
+
+/// Text as every overlay and panel draws it
+pub fn text_style(color: Color) -> TextStyle {
+  TextStyle {
+    font_size: 14.0,
+    color,
+    ..default()
+  }
+}
+
+pub fn spawn_hud(mut commands: Commands) {
+  commands.spawn(NodeBundle {
+    style: Style {
+      position_type: PositionType::Absolute,
+      top: Val::Px(8.0),
+      left: Val::Px(8.0),
+      flex_direction: FlexDirection::Column,
+      row_gap: Val::Px(4.0),
+      padding: UiRect::all(Val::Px(6.0)),
+      ..default()
+    },
+    background_color: Color::srgba(0.0, 0.0, 0.0, 0.6).into(),
+    ..default()
+  }).insert(Interaction::default()).with_children(|hud| {
+    hud.spawn((TextBundle::from_section("", text_style(Color::WHITE)), ColonyText));
+
+    hud.spawn(TextBundle::from_section("Mined / min", text_style(Color::srgb(0.7, 0.7, 0.7))));
+    hud.spawn(NodeBundle {
+      style: Style {
+        height: Val::Px(GRAPH_HEIGHT),
+        align_items: AlignItems::FlexEnd,
+        column_gap: Val::Px(2.0),
+        ..default()
+      },
+      ..default()
+    }).with_children(|graph| {
+      for index in 0..MINING_HISTORY_MINUTES {
+        graph.spawn((
+          NodeBundle {
+            style: Style {
+              width: Val::Px(GRAPH_BAR_WIDTH),
+              height: Val::Px(0.0),
+              ..default()
+            },
+            background_color: Color::srgb(0.2, 0.5, 0.5).into(),
+            ..default()
+          },
+          MiningGraphBar(index),
+        ));
+      }
+    });
+
+    hud.spawn((TextBundle::from_section("", text_style(Color::srgb(1.0, 0.6, 0.2))), AlertsText));
+  });
+}
+
+type Order = Or<(With<Designation>, With<Blueprint>)>;
+
+/// Player orders and the bots that have taken them on
+#[derive(SystemParam)]
+pub struct JobBoard<'w, 's> {
+  reservations: Res<'w, ReservationSystem>,
+  orders: Query<'w, 's, Entity, Order>,
+}
+
+impl JobBoard<'_, '_> {
+  /// Designations and blueprints no bot has taken on yet
+  fn open(&self) -> usize {
+    self.orders.iter().filter(|entity| !self.reservations.is_reserved(&ReservationKey::Entity(*entity))).count()
+  }
+}
+
+pub fn update_colony_text(
+  stockpile: Res<Stockpile>,
+  population: Res<Population>,
+  board: JobBoard,
+  power: Res<PowerGrid>,
+  bots: Query<&Bot>,
+  factories: Query<&BotFactory>,
+  mut texts: Query<&mut Text, With<ColonyText>>,
+) {
+  let total = bots.iter().count();
+  let idle = bots.iter().filter(|bot| bot.job.is_none()).count();
+  let charging = bots.iter()
+    .filter(|bot| bot.job.as_ref().is_some_and(|job| matches!(job.kind, JobKind::Charge { .. })))
+    .count();
+  let queued: usize = factories.iter().map(|factory| factory.queue.len()).sum();
+
+  let resources = if stockpile.items.is_empty() {
+    "none".to_string()
+  } else {
+    stockpile.items.iter()
+      .map(|(item, amount)| format!("{}: {}", item, amount))
+      .collect::<Vec<_>>()
+      .join(", ")
+  };
+
+  for mut text in texts.iter_mut() {
+    text.sections[0].value = format!(
+      "Resources: {}\nBots: {} / {} (idle {}, working {}, charging {}, queued {})\nOpen jobs: {}  Reservations: {}\nPower: {} / {}",
+      resources,
+      total,
+      population.cap,
+      idle,
+      total - idle - charging,
+      charging,
+      queued,
+      board.open(),
+      board.reservations.count(),
+      power.supply(),
+      power.demand(),
+    );
+  }
+}
+
+pub fn update_mining_graph(
+  stats: Res<ColonyStats>,
+  mut bars: Query<(&MiningGraphBar, &mut Style)>,
+) {
+  if !stats.is_changed() {
+    return;
+  }
+
+  let peak = stats.mined_per_minute.iter().copied().max().unwrap_or(0).max(1);
+  // Right-align the history so the current minute is always the last bar
+  let offset = MINING_HISTORY_MINUTES - stats.mined_per_minute.len().min(MINING_HISTORY_MINUTES);
+
+  for (bar, mut style) in bars.iter_mut() {
+    let value = bar.0.checked_sub(offset)
+      .and_then(|index| stats.mined_per_minute.get(index))
+      .copied()
+      .unwrap_or(0);
+    style.height = Val::Px(GRAPH_HEIGHT * value as f32 / peak as f32);
+  }
+}
+
+pub fn update_alerts_text(
+  alerts: Res<Alerts>,
+  mut texts: Query<&mut Text, With<AlertsText>>,
+) {
+  if !alerts.is_changed() {
+    return;
+  }
+
+  let message = alerts.iter()
+    .take(MAX_ALERTS_SHOWN)
+    .map(|alert| format!("! {}", alert.message))
+    .collect::<Vec<_>>()
+    .join("\n");
+
+  for mut text in texts.iter_mut() {
+    text.sections[0].value = message.clone();
+  }
+}
@@ -0,0 +1,167 @@
+use bevy::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use crate::grid::{Grid, Position, Cursor};
+use crate::groups::{Group, BotGroups};
+use crate::pathfinding::Path;
+use crate::entities::bot::Bot;
+
+/// A passable building that bots have to wait on while it opens
+#[derive(Component, Debug)]
+pub struct Door {
+  /// Ticks it takes to open fully from closed
+  pub open_ticks: u32,
+  /// How far open the door is, from 0 to `open_ticks`
+  pub progress: u32,
+  /// Groups whose bots can't path through it
+  pub locked_for: BTreeSet<String>,
+}
+
+impl Door {
+  pub fn new(open_ticks: u32) -> Self {
+    Self { open_ticks, progress: 0, locked_for: BTreeSet::new() }
+  }
+
+  pub fn is_open(&self) -> bool {
+    self.progress >= self.open_ticks
+  }
+
+  /// Ticks a bot would wait for the door to finish opening
+  pub fn remaining_ticks(&self) -> u32 {
+    self.open_ticks.saturating_sub(self.progress)
+  }
+
+  /// Whether members of `group` are kept out. Things in no group, like
+  /// hostile drones, can never open doors.
+  pub fn is_locked_for(&self, group: Option<&Group>) -> bool {
+    group.is_none_or(|group| self.locked_for.contains(&group.0))
+  }
+}
+
+/// Everything a bot of `group` can't walk through: the `Impassable` entities
+/// plus the doors locked to its group
+pub fn blocked_for(group: Option<&Group>, impassable: &HashSet<Entity>, doors: &Query<(Entity, &Position, &Door)>) -> HashSet<Entity> {
+  let mut blocked = impassable.clone();
+  blocked.extend(doors.iter()
+    .filter(|(_, _, door)| door.is_locked_for(group))
+    .map(|(entity, _, _)| entity));
+  blocked
+}
+
+/// Whether a door on `position` is still too closed to step through
+pub fn is_closed_door(position: &Position, grid: &Grid, doors: &Query<&Door>) -> bool {
+  grid.residents(position).iter()
+    .any(|entity| doors.get(*entity).is_ok_and(|door| !door.is_open()))
+}
+
+/// Extra ticks pathfinding charges for stepping onto a door tile, so bots
+/// prefer open doors and open ground over waiting
+pub fn door_costs(doors: &Query<(Entity, &Position, &Door)>) -> HashMap<Position, u32> {
+  doors.iter()
+    .map(|(_, position, door)| (*position, door.remaining_ticks()))
+    .collect()
+}
+
+/// Opens doors a bot is standing in or about to step into, and lets the rest swing shut
+pub fn operate_doors(
+  mut doors: Query<(&Position, &mut Door)>,
+  travellers: Query<(&Position, Option<&Path>), With<Bot>>,
+) {
+  let wanted: HashSet<Position> = travellers.iter()
+    .flat_map(|(position, path)| {
+      let next = path.and_then(|path| path.path.first().copied());
+      [Some(*position), next]
+    })
+    .flatten()
+    .collect();
+
+  for (position, mut door) in doors.iter_mut() {
+    if wanted.contains(position) {
+      if door.progress < door.open_ticks {
+        door.progress += 1;
+      }
+    } else if door.progress > 0 {
+      door.progress -= 1;
+    }
+  }
+}
+
+/// Press L over a door to cycle which group it is locked for
+pub fn lock_doors(
+  keys: Res<ButtonInput<KeyCode>>,
+  cursor: Cursor,
+  groups: Res<BotGroups>,
+  mut doors: Query<(&Position, &mut Door)>,
+) {
+  if !keys.just_pressed(KeyCode::KeyL) {
+    return;
+  }
+  let Some(tile) = cursor.tile() else {
+    return;
+  };
+
+  let names = groups.groups.keys();
+  for (_, mut door) in doors.iter_mut().filter(|(position, _)| **position == tile) {
+    // Cycles unlocked -> locked for each group in turn -> unlocked
+    let next = match door.locked_for.iter().next() {
+      None => names.clone().next(),
+      Some(current) => names.clone().skip_while(|name| *name != current).nth(1),
+    };
+    door.locked_for = next.cloned().into_iter().collect();
+  }
+}
+
+pub fn draw_doors(
+  grid: Res<Grid>,
+  doors: Query<(&Position, &Door)>,
+  mut gizmos: Gizmos,
+) {
+  let half = grid.tile_size * 0.5;
+
+  for (position, door) in doors.iter() {
+    let center = grid.world_position(position);
+    let open = if door.open_ticks == 0 { 1.0 } else { door.progress as f32 / door.open_ticks as f32 };
+    let color = if door.locked_for.is_empty() {
+      Color::srgb(0.8, 0.7, 0.4)
+    } else {
+      Color::srgb(0.9, 0.2, 0.2)
+    };
+
+    // Two leaves that slide apart as the door opens
+    let leaf = half * (1.0 - open);
+    gizmos.line_2d(center + Vec2::new(-half, 0.0), center + Vec2::new(-half + leaf, 0.0), color);
+    gizmos.line_2d(center + Vec2::new(half, 0.0), center + Vec2::new(half - leaf, 0.0), color);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bevy::ecs::system::SystemState;
+
+  #[test]
+  fn stepping_onto_a_door_costs_its_remaining_opening_time() {
+    let mut world = World::new();
+    let mut ajar = Door::new(8);
+    ajar.progress = 5;
+    let mut open = Door::new(8);
+    open.progress = 8;
+    world.spawn((Position::new(1, 1), Door::new(8)));
+    world.spawn((Position::new(2, 1), ajar));
+    world.spawn((Position::new(3, 1), open));
+
+    let mut state: SystemState<Query<(Entity, &Position, &Door)>> = SystemState::new(&mut world);
+    let costs = door_costs(&state.get(&world));
+    assert_eq!(costs[&Position::new(1, 1)], 8);
+    assert_eq!(costs[&Position::new(2, 1)], 3);
+    assert_eq!(costs[&Position::new(3, 1)], 0);
+  }
+
+  #[test]
+  fn doors_stay_shut_to_locked_groups_and_hostiles() {
+    let mut door = Door::new(8);
+    door.locked_for.insert("miners".to_string());
+    assert!(door.is_locked_for(Some(&Group("miners".to_string()))));
+    assert!(!door.is_locked_for(Some(&Group("builders".to_string()))));
+    assert!(door.is_locked_for(None));
+  }
+}
@@ -1,37 +1,57 @@
 use bevy::prelude::*;
+use crate::power::PowerNode;
 
 #[derive(Component)]
 pub struct Interaction {
   pub actor: Entity,
-  pub target: Entity,
+  /// Building whose power network drives the work, if any
+  pub powered_by: Option<Entity>,
   pub ticks_to_complete: u32,
   pub ticks_completed: u32,
   pub completed: bool,
   pub progress_bar_entity: Option<Entity>,
+  /// Fraction of a tick banked while running on short power
+  partial_tick: f32,
 }
 
 impl Interaction {
-  pub fn new(actor: Entity, target: Entity, ticks_to_complete: u32) -> Self {
+  pub fn new(actor: Entity, ticks_to_complete: u32) -> Self {
     Self {
       actor,
-      target,
       ticks_to_complete,
+      powered_by: None,
       ticks_completed: 0,
       completed: false,
       progress_bar_entity: None,
+      partial_tick: 0.0,
     }
   }
+
+  /// Runs the interaction at the speed `building`'s power network allows
+  pub fn powered_by(mut self, building: Entity) -> Self {
+    self.powered_by = Some(building);
+    self
+  }
 }
 
 pub fn update_interactions(
   mut interactions: Query<&mut Interaction>,
+  power: Query<&PowerNode>,
 ) {
   for mut interaction in interactions.iter_mut() {
+    let rate = interaction.powered_by
+      .and_then(|building| power.get(building).ok())
+      .map_or(1.0, PowerNode::rate);
+    interaction.partial_tick += rate;
+    if interaction.partial_tick < 1.0 {
+      continue;
+    }
+    interaction.partial_tick -= 1.0;
+
     interaction.ticks_completed += 1;
     if interaction.ticks_completed >= interaction.ticks_to_complete {
       interaction.completed = true;
       interaction.ticks_completed = interaction.ticks_to_complete;
     }
-    info!("Updating interaction for actor {:?}, ticks completed: {:?}, max: {:?}", interaction.actor, interaction.ticks_completed, interaction.ticks_to_complete);
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+/// A bag of items keyed by item name with a shared capacity
+#[derive(Clone, Debug, Default)]
+pub struct Inventory {
+  pub items: BTreeMap<String, u32>,
+  pub capacity: u32,
+}
+
+impl Inventory {
+  pub fn new(capacity: u32) -> Self {
+    Self { items: BTreeMap::new(), capacity }
+  }
+
+  pub fn count(&self, item: &str) -> u32 {
+    self.items.get(item).copied().unwrap_or(0)
+  }
+
+  pub fn total(&self) -> u32 {
+    self.items.values().sum()
+  }
+
+  pub fn free_space(&self) -> u32 {
+    self.capacity.saturating_sub(self.total())
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.items.is_empty()
+  }
+
+  /// Adds as much as fits and returns how many were added
+  pub fn add(&mut self, item: &str, amount: u32) -> u32 {
+    let added = amount.min(self.free_space());
+    if added > 0 {
+      *self.items.entry(item.to_string()).or_insert(0) += added;
+    }
+    added
+  }
+
+  /// Removes up to `amount` and returns how many were removed
+  pub fn remove(&mut self, item: &str, amount: u32) -> u32 {
+    let Some(count) = self.items.get_mut(item) else {
+      return 0;
+    };
+
+    let removed = amount.min(*count);
+    *count -= removed;
+    if *count == 0 {
+      self.items.remove(item);
+    }
+    removed
+  }
+
+  pub fn first_item(&self) -> Option<(&str, u32)> {
+    self.items.iter().next().map(|(item, count)| (item.as_str(), *count))
+  }
+}
+
+/// Items a bot is carrying
+#[derive(Component, Clone, Debug)]
+pub struct Cargo(pub Inventory);
+
+/// Colony storage that bots deliver to and fetch from
+#[derive(Component, Clone, Debug)]
+pub struct Storage(pub Inventory);
+
+/// Items delivered to a building for it to consume
+#[derive(Component, Clone, Debug)]
+pub struct InputBuffer(pub Inventory);
+
+/// Items a building has produced and is waiting to have hauled away
+#[derive(Component, Clone, Debug)]
+pub struct OutputBuffer(pub Inventory);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn add_stops_at_capacity() {
+    let mut inventory = Inventory::new(10);
+    assert_eq!(inventory.add("scrap", 7), 7);
+    assert_eq!(inventory.add("plate", 5), 3);
+    assert_eq!(inventory.add("plate", 1), 0);
+    assert_eq!(inventory.total(), 10);
+    assert_eq!(inventory.free_space(), 0);
+  }
+
+  #[test]
+  fn remove_takes_what_is_there_and_forgets_emptied_items() {
+    let mut inventory = Inventory::new(10);
+    inventory.add("scrap", 4);
+    assert_eq!(inventory.remove("scrap", 6), 4);
+    assert_eq!(inventory.remove("copper", 1), 0);
+    assert!(inventory.is_empty());
+    assert_eq!(inventory.first_item(), None);
+  }
+}
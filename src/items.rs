@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use bevy::ecs::system::SystemParam;
+use std::collections::HashSet;
+use crate::archetype::Archetypes;
+use crate::grid::{Grid, Position};
+use crate::renderable::Renderable;
+
+/// A stack of items lying loose on a tile. A tile holds at most one stack, and
+/// stacks don't block movement.
+#[derive(Component, Debug)]
+pub struct Item {
+  pub name: String,
+  pub count: u32,
+}
+
+impl Item {
+  /// How many more of `name` fit on this stack
+  pub fn room_for(&self, name: &str, stack_size: u32) -> u32 {
+    if self.name == name {
+      stack_size.saturating_sub(self.count)
+    } else {
+      0
+    }
+  }
+}
+
+/// How many of `name` fit on a tile given the stack already lying there, if any
+pub fn room_on_tile(name: &str, stack_size: u32, stack: Option<&Item>) -> u32 {
+  stack.map_or(stack_size, |stack| stack.room_for(name, stack_size))
+}
+
+pub fn spawn_item(commands: &mut Commands, archetypes: &Archetypes, name: &str, count: u32, position: Position) -> Entity {
+  let (r, g, b) = archetypes.resource(name).map_or((0.7, 0.7, 0.7), |definition| definition.color);
+
+  commands.spawn((
+    Renderable::new(r, g, b).on_floor(0.5),
+    position,
+    Item { name: name.to_string(), count },
+  )).id()
+}
+
+/// The stacks lying on the map, and what it takes to put more down
+#[derive(SystemParam)]
+pub struct Floor<'w, 's> {
+  pub archetypes: Res<'w, Archetypes>,
+  pub grid: Res<'w, Grid>,
+  pub stacks: Query<'w, 's, &'static mut Item>,
+}
+
+impl Floor<'_, '_> {
+  /// Puts up to `count` of `name` on `position`, topping up the stack lying
+  /// there or starting a new one. Returns how many fit.
+  pub fn drop_on_tile(&mut self, commands: &mut Commands, name: &str, count: u32, position: Position) -> u32 {
+    let stack_size = self.archetypes.stack_size(name);
+
+    let stack = self.grid.residents(&position).iter().copied().find(|entity| self.stacks.contains(*entity));
+    match stack.and_then(|entity| self.stacks.get_mut(entity).ok()) {
+      Some(mut stack) => {
+        let dropped = stack.room_for(name, stack_size).min(count);
+        stack.count += dropped;
+        dropped
+      },
+      None => {
+        let dropped = stack_size.min(count);
+        if dropped > 0 {
+          spawn_item(commands, &self.archetypes, name, dropped, position);
+        }
+        dropped
+      },
+    }
+  }
+
+  /// Drops `count` of `name` on the tiles nearest to `start` that have room,
+  /// spilling onto further tiles once a stack is full. Returns how many were
+  /// dropped. Stacks spawned by a call only show up in queries next frame, so
+  /// callers that drop more in the same frame must not reuse their tiles.
+  pub fn drop_near(&mut self, commands: &mut Commands, impassable: &HashSet<Entity>, name: &str, count: u32, start: &Position) -> u32 {
+    let stack_size = self.archetypes.stack_size(name);
+    let mut used = HashSet::new();
+    let mut dropped = 0;
+
+    while dropped < count {
+      let tile = self.grid.nearest_tile(start, impassable, |position| {
+        let stack = self.grid.residents(position).iter().find_map(|entity| self.stacks.get(*entity).ok());
+        !used.contains(position) && room_on_tile(name, stack_size, stack) > 0
+      });
+      let Some(tile) = tile else {
+        break;
+      };
+
+      used.insert(tile);
+      dropped += self.drop_on_tile(commands, name, count - dropped, tile);
+    }
+
+    dropped
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn a_stack_only_takes_its_own_item() {
+    let stack = Item { name: "scrap".to_string(), count: 30 };
+    assert_eq!(stack.room_for("scrap", 50), 20);
+    assert_eq!(stack.room_for("plate", 50), 0);
+    assert_eq!(stack.room_for("scrap", 20), 0);
+  }
+
+  #[test]
+  fn an_empty_tile_holds_a_full_stack() {
+    let stack = Item { name: "plate".to_string(), count: 10 };
+    assert_eq!(room_on_tile("scrap", 50, None), 50);
+    assert_eq!(room_on_tile("plate", 30, Some(&stack)), 20);
+    assert_eq!(room_on_tile("scrap", 50, Some(&stack)), 0);
+  }
+}
@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use crate::grid::Position;
+use crate::reservation::{ReservationSystem, ReservationKey};
+
+/// Where a haul ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dropoff {
+  /// A storage building or a building's input buffer
+  Building(Entity),
+  /// A stockpile zone tile, where the items are left loose
+  Tile(Position),
+}
+
+impl Dropoff {
+  fn label(&self) -> String {
+    match self {
+      Dropoff::Building(entity) => entity.index().to_string(),
+      Dropoff::Tile(position) => format!("({}, {})", position.x, position.y),
+    }
+  }
+}
+
+/// The kinds of work a bot group can be allowed to take. Charging and
+/// delivering what a bot already carries are always allowed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum JobType {
+  Haul,
+  Install,
+  Build,
+  Repair,
+  Deconstruct,
+  Research,
+  Mine,
+  Explore,
+}
+
+impl JobType {
+  pub const ALL: [JobType; 8] = [
+    JobType::Haul,
+    JobType::Install,
+    JobType::Build,
+    JobType::Repair,
+    JobType::Deconstruct,
+    JobType::Research,
+    JobType::Mine,
+    JobType::Explore,
+  ];
+
+  pub fn label(&self) -> &'static str {
+    match self {
+      JobType::Haul => "haul",
+      JobType::Install => "install",
+      JobType::Build => "build",
+      JobType::Repair => "repair",
+      JobType::Deconstruct => "deconstruct",
+      JobType::Research => "research",
+      JobType::Mine => "mine",
+      JobType::Explore => "explore",
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum JobKind {
+  /// Mine a resource node and drop the yield on the floor around the bot
+  Mine { target: Entity },
+  /// Move items into `destination`. The `source` is a building or a loose
+  /// item; without one the items are already in cargo.
+  Haul {
+    source: Option<Entity>,
+    destination: Dropoff,
+    item: String,
+    amount: u32,
+    picked_up: bool,
+  },
+  /// Fetch a module item from `source` and have it installed at `workshop`
+  Install {
+    source: Entity,
+    workshop: Entity,
+    module: String,
+    picked_up: bool,
+  },
+  /// Tear down a building designated for deconstruction
+  Deconstruct { target: Entity },
+  /// Build a blueprint whose cost has been delivered
+  Build { site: Entity },
+  /// Restore a damaged building or bot to full health
+  Repair { target: Entity },
+  /// Walk to the edge of the explored map to see past it
+  Explore { target: Position },
+  /// Work a lab for one session of research points
+  Research { lab: Entity },
+  /// Refill the bot's battery at a charger
+  Charge { charger: Entity },
+  /// Walk back to the group's home depot to wait out an off shift
+  GoHome { home: Entity },
+}
+
+#[derive(Clone, Debug)]
+pub struct Job {
+  pub kind: JobKind,
+  /// Everything this job holds in the `ReservationSystem`, released together
+  pub reservations: Vec<ReservationKey>,
+}
+
+impl Job {
+  pub fn new(kind: JobKind) -> Self {
+    Self { kind, reservations: Vec::new() }
+  }
+
+  /// Reserves all keys for `bot`, or none of them if any is already taken
+  pub fn try_reserve(&mut self, keys: Vec<ReservationKey>, bot: Entity, reservations: &mut ReservationSystem) -> bool {
+    if keys.iter().any(|key| reservations.is_reserved(key)) {
+      return false;
+    }
+
+    for key in keys {
+      reservations.try_reserve(key.clone(), bot);
+      self.reservations.push(key);
+    }
+    true
+  }
+
+  pub fn release(&self, reservations: &mut ReservationSystem) {
+    for key in &self.reservations {
+      reservations.unreserve(key);
+    }
+  }
+
+  pub fn label(&self) -> String {
+    match &self.kind {
+      JobKind::Mine { target } => format!("mine {}", target.index()),
+      JobKind::Haul { destination, item, amount, .. } => format!("haul {} {} to {}", amount, item, destination.label()),
+      JobKind::Deconstruct { target } => format!("deconstruct {}", target.index()),
+      JobKind::Build { site } => format!("build {}", site.index()),
+      JobKind::Repair { target } => format!("repair {}", target.index()),
+      JobKind::Explore { target } => format!("explore ({}, {})", target.x, target.y),
+      JobKind::Research { lab } => format!("research at {}", lab.index()),
+      JobKind::Charge { charger } => format!("charge at {}", charger.index()),
+      JobKind::GoHome { home } => format!("go home to {}", home.index()),
+      JobKind::Install { module, workshop, .. } => format!("install {} at {}", module, workshop.index()),
+    }
+  }
+}
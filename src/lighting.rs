@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use crate::grid::{Grid, Position};
+use crate::archetype::LightDefinition;
+use crate::simulation::SimulationClock;
+use crate::power::PowerNode;
+use crate::pathfinding::distance;
+
+/// Hour the sun starts to rise, and the one it starts to set
+const DAWN_HOUR: f32 = 6.0;
+const DUSK_HOUR: f32 = 18.0;
+/// Hours the sun takes to rise or set
+const TWILIGHT_HOURS: f32 = 2.0;
+/// Light left in the middle of the night, in `0.0..=1.0`
+const NIGHT_LIGHT: f32 = 0.35;
+/// Above entities, below interaction progress bars
+const SHADE_Z: f32 = 1.5;
+
+/// A building that lights up the tiles around it while it has power
+#[derive(Component)]
+pub struct Lamp(pub LightDefinition);
+
+/// Darkens one tile by however much light it lacks
+#[derive(Component)]
+pub struct LightShade {
+  position: Position,
+}
+
+/// How high the sun is, from 0.0 at night to 1.0 through the day
+pub fn sunlight(clock: &SimulationClock) -> f32 {
+  let hour = clock.time_of_day();
+  if !(DAWN_HOUR..DUSK_HOUR + TWILIGHT_HOURS).contains(&hour) {
+    0.0
+  } else if hour < DAWN_HOUR + TWILIGHT_HOURS {
+    (hour - DAWN_HOUR) / TWILIGHT_HOURS
+  } else if hour < DUSK_HOUR {
+    1.0
+  } else {
+    1.0 - (hour - DUSK_HOUR) / TWILIGHT_HOURS
+  }
+}
+
+pub fn spawn_light_shades(mut commands: Commands, grid: Res<Grid>) {
+  for tile in grid.tiles.iter().flatten() {
+    let world = grid.world_position(&tile.position);
+    commands.spawn((
+      SpriteBundle {
+        sprite: Sprite {
+          color: Color::NONE,
+          custom_size: Some(Vec2::splat(grid.tile_size)),
+          ..default()
+        },
+        transform: Transform::from_xyz(world.x, world.y, SHADE_Z),
+        ..default()
+      },
+      LightShade { position: tile.position },
+    ));
+  }
+}
+
+type LampChanged = (With<Lamp>, Or<(Changed<Lamp>, Changed<PowerNode>, Changed<Position>)>);
+
+/// Tints every tile for the time of day, lifting the dark around powered
+/// lamps. A lamp is brightest on its own tile and fades out to its radius.
+/// Reshades when the clock moves or a lamp is built, changes or goes away.
+pub fn shade_light(
+  clock: Res<SimulationClock>,
+  lamps: Query<(&Position, &Lamp, Option<&PowerNode>)>,
+  changed: Query<(), LampChanged>,
+  mut removed: RemovedComponents<Lamp>,
+  mut shades: Query<(&LightShade, &mut Sprite)>,
+) {
+  // Read every removal so none are left to trigger a later frame
+  let removed = removed.read().count() > 0;
+  if !clock.is_changed() && changed.is_empty() && !removed {
+    return;
+  }
+
+  let ambient = NIGHT_LIGHT + (1.0 - NIGHT_LIGHT) * sunlight(&clock);
+  let lit: Vec<(&Position, f32, f32)> = lamps.iter()
+    .map(|(position, lamp, power)| (position, lamp.0.radius, power.map_or(1.0, PowerNode::rate)))
+    .filter(|(_, radius, rate)| *radius > 0.0 && *rate > 0.0)
+    .collect();
+
+  for (shade, mut sprite) in shades.iter_mut() {
+    let light = lit.iter()
+      .map(|(position, radius, rate)| (1.0 - distance(position, &shade.position) / radius).max(0.0) * rate)
+      .fold(ambient, f32::max);
+    sprite.color = Color::srgba(0.02, 0.03, 0.12, 1.0 - light);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bevy::ecs::system::RunSystemOnce;
+
+  #[test]
+  fn the_sun_is_up_by_day_and_down_by_night() {
+    assert_eq!(sunlight(&SimulationClock::at_hour(0)), 0.0);
+    assert_eq!(sunlight(&SimulationClock::at_hour(12)), 1.0);
+    assert_eq!(sunlight(&SimulationClock::at_hour(21)), 0.0);
+  }
+
+  #[test]
+  fn the_sun_rises_and_sets_over_twilight() {
+    assert_eq!(sunlight(&SimulationClock::at_hour(7)), 0.5);
+    assert_eq!(sunlight(&SimulationClock::at_hour(19)), 0.5);
+  }
+
+  #[test]
+  fn lamps_light_up_the_night_around_them() {
+    let mut world = World::new();
+    world.insert_resource(SimulationClock::at_hour(0));
+    world.spawn((Position::new(5, 5), Lamp(LightDefinition { radius: 4.0 })));
+    let lit = world.spawn((Sprite::default(), LightShade { position: Position::new(5, 5) })).id();
+    let dark = world.spawn((Sprite::default(), LightShade { position: Position::new(15, 15) })).id();
+
+    world.run_system_once(shade_light);
+    let darkness = |entity| world.get::<Sprite>(entity).unwrap().color.alpha();
+    assert_eq!(darkness(lit), 0.0);
+    assert_eq!(darkness(dark), 1.0 - NIGHT_LIGHT);
+  }
+}
@@ -6,14 +6,39 @@ mod reservation;
 mod pathfinding;
 mod movement;
 mod interact;
+mod simulation;
+mod atlas;
+mod archetype;
+mod colony;
+mod hud;
+mod debug;
+mod inventory;
+mod jobs;
+mod modules;
+mod energy;
+mod research;
+mod items;
+mod zones;
+mod designation;
+mod groups;
+mod schedule;
+mod lighting;
+mod impass;
+mod health;
+mod hostiles;
+mod events;
+mod spawners;
+mod fog;
+mod power;
+mod conveyors;
 
 use bevy::prelude::*;
 use reservation::ReservationSystem;
 use renderable::SpriteMapping;
-
-fn init() -> Result<(), String> {
-    Ok(())
-}
+use simulation::{SimulationControl, SimulationClock, SimulationRng};
+use colony::{Stockpile, Population, ColonyStats, Alerts};
+use debug::DebugOverlays;
+use archetype::{ArchetypeLibrary, ArchetypeLoader};
 
 fn main() -> Result<(), String> {
     App::new()
@@ -24,23 +49,133 @@ fn main() -> Result<(), String> {
                 ..default()
             }),
             ..default()
-        }))
+        }).set(ImagePlugin::default_nearest()))
+        .init_asset::<ArchetypeLibrary>()
+        .init_asset_loader::<ArchetypeLoader>()
         .init_resource::<ReservationSystem>()
         .init_resource::<SpriteMapping>()
-        .insert_resource(Time::<Fixed>::from_hz(10.0))
+        .init_resource::<SimulationControl>()
+        .init_resource::<SimulationClock>()
+        .init_resource::<SimulationRng>()
+        .init_resource::<Stockpile>()
+        .init_resource::<Population>()
+        .init_resource::<research::Research>()
+        .init_resource::<zones::ZoneTool>()
+        .init_resource::<designation::DesignationTool>()
+        .init_resource::<hostiles::HostileWaves>()
+        .init_resource::<events::EventScheduler>()
+        .init_resource::<fog::FogOfWar>()
+        .init_resource::<power::PowerGrid>()
+        .init_resource::<groups::BotGroups>()
+        .init_resource::<ColonyStats>()
+        .init_resource::<Alerts>()
+        .init_resource::<DebugOverlays>()
+        .insert_resource(Time::<Fixed>::from_hz(simulation::BASE_TICK_HZ))
         .add_systems(Startup, (setup_camera, grid::setup_grid))
-        .add_systems(Startup, spawn::spawn_initial_components.after(grid::setup_grid))
+        .add_systems(Startup, lighting::spawn_light_shades.after(grid::setup_grid))
+        .add_systems(Startup, archetype::load_archetypes)
+        .add_systems(Startup, simulation::spawn_speed_indicator)
+        .add_systems(Startup, hud::spawn_hud)
+        .add_systems(Startup, debug::spawn_debug_panel)
+        .add_systems(Startup, research::spawn_research_screen)
+        .add_systems(Startup, designation::spawn_tool_indicator)
+        .add_systems(Startup, groups::spawn_group_screen)
+        .add_systems(Startup, atlas::load_sprite_atlas)
+        .add_systems(PreUpdate, simulation::handle_speed_input)
+        .add_systems(PreUpdate, simulation::step_simulation.after(simulation::handle_speed_input))
+        .add_systems(Update, archetype::update_archetypes)
+        .add_systems(Update, spawn::spawn_initial_components
+            .after(archetype::update_archetypes)
+            .run_if(archetype::archetypes_ready.and_then(run_once())))
+        .add_systems(Update, spawn::apply_archetype_changes.after(archetype::update_archetypes))
         .add_systems(Update, grid::add_new_positions_as_residents)
         .add_systems(Update, grid::update_residents)
+        .add_systems(Update, grid::remove_despawned_residents)
+        .add_systems(Update, reservation::release_despawned.run_if(simulation::is_running))
         .add_systems(Update, renderable::spawn_sprites_for_new_renderables)
         .add_systems(Update, renderable::update_sprite_positions)
+        .add_systems(Update, renderable::rotate_sprites_to_facing)
+        .add_systems(Update, renderable::sync_sprite_colors)
         .add_systems(Update, renderable::cleanup_despawned_sprites)
-        .add_systems(Update, entities::bot::find_bot_jobs)
-        .add_systems(Update, entities::bot::work)
-        .add_systems(Update, pathfinding::pathfind)
+        .add_systems(Update, fog::update_visibility.run_if(simulation::is_running))
+        .add_systems(Update, fog::shade_tiles.after(fog::update_visibility).after(atlas::autotile_terrain))
+        .add_systems(Update, fog::hide_unseen.after(fog::update_visibility))
+        .add_systems(Update, lighting::shade_light)
+        .add_systems(Update, entities::bot::find_bot_jobs.run_if(simulation::is_running))
+        .add_systems(Update, entities::bot::work.run_if(simulation::is_running))
+        .add_systems(Update, pathfinding::pathfind.run_if(simulation::is_running))
+        .add_systems(Update, pathfinding::invalidate_blocked_paths.after(pathfinding::pathfind).run_if(simulation::is_running))
+        .add_systems(Update, entities::building::run_fabricators.run_if(simulation::is_running))
+        .add_systems(Update, entities::building::queue_bots)
+        .add_systems(Update, entities::building::run_bot_factories.run_if(simulation::is_running))
+        .add_systems(Update, modules::apply_modules.run_if(simulation::is_running))
+        .add_systems(Update, research::lock_buildings.run_if(simulation::is_running))
+        .add_systems(Update, research::toggle_research_screen)
+        .add_systems(Update, research::rebuild_tech_list.after(archetype::update_archetypes))
+        .add_systems(Update, research::handle_tech_buttons)
+        .add_systems(Update, research::update_research_screen)
+        .add_systems(Update, zones::edit_zones)
+        .add_systems(Update, zones::draw_zones)
+        .add_systems(Update, designation::select_designation_tool)
+        .add_systems(Update, designation::designate_area)
+        .add_systems(Update, designation::draw_designations)
+        .add_systems(Update, designation::update_tool_indicator)
+        .add_systems(Update, groups::toggle_group_screen)
+        .add_systems(Update, groups::rebuild_group_list)
+        .add_systems(Update, groups::handle_group_buttons)
+        .add_systems(Update, groups::assign_bots)
+        .add_systems(Update, groups::draw_group_area)
+        .add_systems(Update, impass::lock_doors)
+        .add_systems(Update, impass::draw_doors)
+        .add_systems(Update, health::draw_health_bars)
+        .add_systems(Update, power::toggle_power_overlay)
+        .add_systems(Update, power::draw_power_overlay)
+        .add_systems(Update, conveyors::draw_headings)
+        .add_systems(Update, hostiles::draw_shots)
+        .add_systems(Update, colony::tally_stockpile)
+        .add_systems(Update, colony::tally_population)
+        .add_systems(Update, atlas::check_sprite_atlas)
+        .add_systems(Update, atlas::attach_atlas_sprites.after(atlas::check_sprite_atlas))
+        .add_systems(Update, atlas::autotile_terrain.after(atlas::check_sprite_atlas))
+        .add_systems(Update, atlas::animate_sprites)
         .add_systems(Update, renderable::draw_interaction_progress_bars)
+        .add_systems(Update, simulation::update_speed_indicator)
+        .add_systems(Update, hud::update_colony_text)
+        .add_systems(Update, hud::update_mining_graph)
+        .add_systems(Update, hud::update_alerts_text)
+        .add_systems(Update, debug::toggle_debug_overlays)
+        .add_systems(Update, debug::draw_path_overlay)
+        .add_systems(Update, debug::draw_reservation_overlay)
+        .add_systems(Update, debug::draw_impassable_overlay)
+        .add_systems(Update, debug::draw_region_overlay)
+        .add_systems(Update, debug::update_debug_panel)
+        .add_systems(FixedFirst, simulation::advance_clock)
+        .add_systems(FixedPreUpdate, movement::record_previous_positions)
+        .add_systems(FixedUpdate, impass::operate_doors.before(movement::move_along_path))
         .add_systems(FixedUpdate, movement::move_along_path)
+        .add_systems(FixedUpdate, conveyors::run_inserters.before(conveyors::run_conveyors))
+        .add_systems(FixedUpdate, conveyors::run_conveyors)
+        .add_systems(FixedUpdate, movement::advance_movement_timers)
+        .add_systems(FixedUpdate, power::balance_power.before(interact::update_interactions))
         .add_systems(FixedUpdate, interact::update_interactions)
+        .add_systems(FixedUpdate, energy::drain_energy.after(movement::move_along_path))
+        .add_systems(FixedUpdate, colony::advance_stats)
+        .add_systems(FixedUpdate, colony::expire_alerts)
+        .add_systems(FixedUpdate, colony::detect_stalled_bots)
+        .add_systems(FixedUpdate, hostiles::spawn_waves)
+        .add_systems(FixedUpdate, events::run_events.after(hostiles::spawn_waves))
+        .add_systems(FixedUpdate, spawners::run_spawners.after(events::run_events))
+        .add_systems(FixedUpdate, hostiles::hunt)
+        .add_systems(FixedUpdate, hostiles::fire_turrets)
+        .add_systems(FixedUpdate, hostiles::armed_bots_attack)
+        .add_systems(FixedUpdate, health::wear_bots)
+        .add_systems(FixedUpdate, health::break_down_bots
+            .after(hostiles::hunt)
+            .after(health::wear_bots))
+        .add_systems(FixedUpdate, health::despawn_dead
+            .after(hostiles::hunt)
+            .after(hostiles::fire_turrets)
+            .after(hostiles::armed_bots_attack))
         .run();
 
     Ok(())
@@ -48,4 +183,4 @@ fn main() -> Result<(), String> {
 
 fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
-}
\ No newline at end of file
+}
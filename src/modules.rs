@@ -0,0 +1,124 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use crate::archetype::{Archetype, Archetypes, BotDefinition};
+use crate::entities::bot::Bot;
+use crate::energy::Energy;
+use crate::inventory::Cargo;
+use crate::movement::MovementSpeed;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ModuleSlot {
+  Drill,
+  CargoBay,
+  Treads,
+  Battery,
+  Weapon,
+}
+
+/// Modules installed on a bot, at most one per slot, by module name
+#[derive(Component, Default, Debug)]
+pub struct Modules {
+  pub installed: BTreeMap<ModuleSlot, String>,
+  /// Uses each installed module has had, for modules that wear out
+  pub wear: BTreeMap<ModuleSlot, u32>,
+}
+
+impl Modules {
+  pub fn is_free(&self, slot: ModuleSlot) -> bool {
+    !self.installed.contains_key(&slot)
+  }
+
+  pub fn install(&mut self, slot: ModuleSlot, name: String) {
+    self.installed.insert(slot, name);
+    self.wear.remove(&slot);
+  }
+
+  /// Best tool tier among the installed modules
+  pub fn tool_tier(&self, archetypes: &Archetypes) -> u32 {
+    self.installed.values()
+      .filter_map(|name| archetypes.module(name))
+      .map(|module| module.tier)
+      .max()
+      .unwrap_or(0)
+  }
+
+  /// Counts a use of the module in `slot`, removing it once its durability is
+  /// used up. Returns the name of a module that wore out.
+  pub fn wear_out(&mut self, slot: ModuleSlot, archetypes: &Archetypes) -> Option<String> {
+    let durability = self.installed.get(&slot)
+      .and_then(|name| archetypes.module(name))
+      .and_then(|module| module.durability)?;
+
+    let wear = self.wear.entry(slot).or_insert(0);
+    *wear += 1;
+    if *wear < durability {
+      return None;
+    }
+    self.wear.remove(&slot);
+    self.installed.remove(&slot)
+  }
+}
+
+/// A bot's stats after its modules are applied on top of its archetype
+#[derive(Clone, Copy, Debug)]
+pub struct BotStats {
+  pub work_rate: u32,
+  pub ticks_per_tile: u32,
+  /// Percent added to movement speed
+  pub speed_bonus: u32,
+  pub carry_capacity: u32,
+  pub energy_capacity: u32,
+  pub attack: u32,
+}
+
+impl BotStats {
+  pub fn new(definition: &BotDefinition, modules: &Modules, archetypes: &Archetypes) -> Self {
+    let mut stats = Self {
+      work_rate: definition.work_rate,
+      ticks_per_tile: definition.ticks_per_tile,
+      speed_bonus: 0,
+      carry_capacity: definition.carry_capacity,
+      energy_capacity: definition.energy_capacity,
+      attack: 0,
+    };
+
+    for module in modules.installed.values().filter_map(|name| archetypes.module(name)) {
+      stats.work_rate += module.work_rate;
+      stats.speed_bonus += module.speed;
+      stats.carry_capacity += module.carry_capacity;
+      stats.energy_capacity += module.energy_capacity;
+      stats.attack += module.attack;
+    }
+
+    stats.work_rate = stats.work_rate.max(1);
+    stats
+  }
+
+  pub fn apply(&self, bot: &mut Bot, speed: &mut MovementSpeed, cargo: &mut Cargo, energy: &mut Energy) {
+    bot.work_rate = self.work_rate;
+    bot.attack = self.attack;
+    let pace = MovementSpeed::new(self.ticks_per_tile, self.speed_bonus);
+    if !speed.same_pace(&pace) {
+      *speed = pace;
+    }
+    cargo.0.capacity = self.carry_capacity;
+    energy.set_capacity(self.energy_capacity);
+  }
+}
+
+type BotStatParts = (&'static Archetype, &'static Modules, &'static mut Bot, &'static mut MovementSpeed, &'static mut Cargo, &'static mut Energy);
+
+/// Recomputes the stats of bots whose modules changed
+pub fn apply_modules(
+  archetypes: Res<Archetypes>,
+  mut bots: Query<BotStatParts, Changed<Modules>>,
+) {
+  for (archetype, modules, mut bot, mut speed, mut cargo, mut energy) in bots.iter_mut() {
+    let Some(definition) = archetypes.bot(&archetype.0) else {
+      continue;
+    };
+
+    BotStats::new(definition, modules, &archetypes).apply(&mut bot, &mut speed, &mut cargo, &mut energy);
+  }
+}
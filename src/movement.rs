@@ -1,23 +1,150 @@
 use bevy::prelude::*;
-use crate::grid::Position;
+use crate::grid::{Grid, Position};
 use crate::pathfinding::Path;
+use crate::impass::{self, Door};
 
+/// The logical position an entity had at the start of the current fixed tick.
+/// Used only to interpolate sprites between tiles.
+#[derive(Component, Clone, Copy)]
+pub struct PreviousPosition(pub Position);
+
+/// How fast an entity crosses tiles. It gains `per_tick` progress every fixed
+/// tick and steps to the next tile for every `per_tile` of it, so it can take
+/// several ticks per tile or several tiles per tick. Entities without it move
+/// one tile per tick.
+#[derive(Component)]
+pub struct MovementSpeed {
+  per_tick: u32,
+  per_tile: u32,
+  progress: u32,
+}
+
+impl MovementSpeed {
+  /// Crosses a tile every `ticks_per_tile` ticks, `bonus_percent` faster
+  pub fn new(ticks_per_tile: u32, bonus_percent: u32) -> Self {
+    let per_tick = 100 + bonus_percent;
+    let per_tile = ticks_per_tile.max(1) * 100;
+    Self { per_tick, per_tile, progress: per_tile.saturating_sub(per_tick) }
+  }
+
+  pub fn same_pace(&self, other: &MovementSpeed) -> bool {
+    self.per_tick == other.per_tick && self.per_tile == other.per_tile
+  }
+
+  /// Progress that makes the next tick's gain complete a step, which is as
+  /// much as builds up while standing still
+  fn ready(&self) -> u32 {
+    self.per_tile.saturating_sub(self.per_tick)
+  }
+
+  /// How far the entity has travelled from its previous tile, in `0.0..=1.0`
+  pub fn step_fraction(&self, overstep_fraction: f32) -> f32 {
+    if self.per_tick >= self.per_tile {
+      return overstep_fraction;
+    }
+    ((self.progress as f32 + overstep_fraction * self.per_tick as f32) / self.per_tile as f32).min(1.0)
+  }
+}
+
+/// Direction of the last step an entity took along its path
+#[derive(Component, Clone, Copy)]
+pub struct Facing(pub Vec2);
+
+impl Default for Facing {
+  fn default() -> Self {
+    Self(Vec2::Y)
+  }
+}
+
+/// Entities with a `MovementSpeed` track their previous tile in `move_along_path`
+/// instead, since a single step can span several ticks.
+pub fn record_previous_positions(
+  mut query: Query<(&Position, &mut PreviousPosition), Without<MovementSpeed>>,
+) {
+  for (position, mut previous) in query.iter_mut() {
+    if previous.0 != *position {
+      previous.0 = *position;
+    }
+  }
+}
+
+pub fn advance_movement_timers(mut speeds: Query<&mut MovementSpeed, Without<Path>>) {
+  for mut speed in speeds.iter_mut() {
+    let ready = speed.ready();
+    if speed.progress < ready {
+      speed.progress = (speed.progress + speed.per_tick).min(ready);
+    }
+  }
+}
+
+type Walker = (
+  Entity,
+  &'static mut Path,
+  &'static mut Position,
+  Option<&'static mut MovementSpeed>,
+  Option<&'static mut PreviousPosition>,
+  Option<&'static mut Facing>,
+);
+
+/// Steps entities along their paths, as many tiles each tick as their speed
+/// allows. Without a speed they take one step per tick.
 pub fn move_along_path(
-  mut paths: Query<(Entity, &mut Path, &mut Position)>,
+  mut paths: Query<Walker>,
+  grid: Res<Grid>,
+  doors: Query<&Door>,
   mut commands: Commands,
 ) {
-  for (entity, mut path, mut position) in paths.iter_mut() {
-    if path.path.is_empty() {
-      continue;
+  for (entity, mut path, mut position, mut speed, mut previous, mut facing) in paths.iter_mut() {
+    if let Some(speed) = speed.as_mut() {
+      speed.progress += speed.per_tick;
     }
 
-    let next_position = path.path[0];
-    position.x = next_position.x;
-    position.y = next_position.y;
-    path.path.remove(0);
+    let mut stepped = false;
+    while let Some(&next_position) = path.path.first() {
+      // Wait in front of a door until it has swung fully open
+      if impass::is_closed_door(&next_position, &grid, &doors) {
+        break;
+      }
+
+      match speed.as_mut() {
+        Some(speed) if speed.progress < speed.per_tile => break,
+        Some(speed) => speed.progress -= speed.per_tile,
+        None if stepped => break,
+        None => {},
+      }
+
+      // Sprites slide from where the entity stood before its first step this tick
+      if !stepped && speed.is_some() {
+        if let Some(previous) = previous.as_mut() {
+          previous.0 = *position;
+        }
+      }
+      stepped = true;
+
+      if let Some(facing) = facing.as_mut() {
+        let direction = Vec2::new(
+          next_position.x as f32 - position.x as f32,
+          next_position.y as f32 - position.y as f32,
+        );
+        if direction != Vec2::ZERO {
+          facing.0 = direction.normalize();
+        }
+      }
+
+      position.x = next_position.x;
+      position.y = next_position.y;
+      path.path.remove(0);
+    }
+
+    // Progress doesn't build up while blocked or at the end of the path
+    if let Some(speed) = speed.as_mut() {
+      if speed.progress >= speed.per_tile {
+        speed.progress = speed.ready();
+      }
+    }
 
-    if path.path.is_empty() {
+    if stepped && path.path.is_empty() {
       commands.entity(entity).remove::<Path>();
     }
   }
-}
\ No newline at end of file
+}
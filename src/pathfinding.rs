@@ -2,6 +2,11 @@ use bevy::prelude::*;
 use crate::grid::{Position, Grid, Impassable};
 use std::collections::{HashMap, BinaryHeap};
 use std::cmp::Ordering;
+use crate::colony::{Alerts, AlertKind};
+use crate::simulation::SimulationClock;
+use crate::groups::Group;
+use crate::impass::{self, Door};
+use crate::entities::bot::Bot;
 
 #[derive(Component)]
 pub struct Path {
@@ -40,8 +45,8 @@ impl PartialOrd for State {
 }
 
 fn heuristic(a: &Position, b: &Position) -> u32 {
-  let dx = (a.x as i32 - b.x as i32).abs() as u32;
-  let dy = (a.y as i32 - b.y as i32).abs() as u32;
+  let dx = (a.x as i32 - b.x as i32).unsigned_abs();
+  let dy = (a.y as i32 - b.y as i32).unsigned_abs();
   dx + dy
 }
 
@@ -64,7 +69,8 @@ fn is_tile_passable(position: &Position, grid: &Grid, impassable_entities: &std:
   }
 }
 
-fn find_passable_adjacent_tile(goal: &Position, grid: &Grid, impassable_entities: &std::collections::HashSet<Entity>) -> Option<Position> {
+/// First passable tile orthogonally next to `goal`
+pub fn find_passable_adjacent_tile(goal: &Position, grid: &Grid, impassable_entities: &std::collections::HashSet<Entity>) -> Option<Position> {
   let directions = [
     (goal.x.wrapping_sub(1), goal.y),
     (goal.x + 1, goal.y),
@@ -86,13 +92,17 @@ fn find_passable_adjacent_tile(goal: &Position, grid: &Grid, impassable_entities
   None
 }
 
-fn astar(start: &Position, goal: &Position, grid: &Grid, impassable_entities: &std::collections::HashSet<Entity>) -> Option<Vec<Position>> {
+/// Shortest path by ticks spent, where each step costs one plus the tile's
+/// entry in `step_costs`
+fn astar(
+  start: &Position,
+  goal: &Position,
+  grid: &Grid,
+  impassable_entities: &std::collections::HashSet<Entity>,
+  step_costs: &HashMap<Position, u32>,
+) -> Option<Vec<Position>> {
   let actual_goal = if !is_tile_passable(goal, grid, impassable_entities) {
-    if let Some(adjacent_goal) = find_passable_adjacent_tile(goal, grid, impassable_entities) {
-      adjacent_goal
-    } else {
-      return None;
-    }
+    find_passable_adjacent_tile(goal, grid, impassable_entities)?
   } else {
     *goal
   };
@@ -132,7 +142,7 @@ fn astar(start: &Position, goal: &Position, grid: &Grid, impassable_entities: &s
         continue;
       }
 
-      let tentative_g = current_g + 1;
+      let tentative_g = current_g + 1 + step_costs.get(&neighbor).copied().unwrap_or(0);
       let neighbor_g = *g_score.get(&neighbor).unwrap_or(&u32::MAX);
 
       if tentative_g < neighbor_g {
@@ -150,14 +160,21 @@ fn astar(start: &Position, goal: &Position, grid: &Grid, impassable_entities: &s
   None
 }
 
+type Traveller = (Entity, &'static mut Path, &'static Position, Option<&'static Group>, Has<Bot>);
+
 pub fn pathfind(
   grid: Res<Grid>,
   impassable: Query<Entity, With<Impassable>>,
-  mut paths: Query<(&mut Path, &Position)>,
+  doors: Query<(Entity, &Position, &Door)>,
+  mut paths: Query<Traveller>,
+  mut alerts: ResMut<Alerts>,
+  clock: Res<SimulationClock>,
 ) {
   let impassable_set: std::collections::HashSet<Entity> = impassable.iter().collect();
+  // Waiting for a door to open counts against routes through it
+  let door_costs = impass::door_costs(&doors);
 
-  for (mut path, current_position) in paths.iter_mut() {
+  for (entity, mut path, current_position, group, is_bot) in paths.iter_mut() {
     if !path.path.is_empty() {
       continue;
     }
@@ -166,11 +183,35 @@ pub fn pathfind(
       continue;
     }
 
-    if let Some(found_path) = astar(current_position, &path.target, &grid, &impassable_set) {
+    let blocked = impass::blocked_for(group, &impassable_set, &doors);
+    if let Some(found_path) = astar(current_position, &path.target, &grid, &blocked, &door_costs) {
       path.path = found_path;
-      info!("Path found, path: {:?}", path.path);
+      debug!("Path found, path: {:?}", path.path);
+      alerts.clear(&AlertKind::UnreachableJob(entity));
     } else {
-      warn!("Failed to find path");
+      debug!("Failed to find path");
+      // Hostile drones share the pathfinding, but only bots hold jobs to warn about
+      if is_bot {
+        let message = format!("Bot {} can't reach ({}, {})", entity.index(), path.target.x, path.target.y);
+        alerts.raise(AlertKind::UnreachableJob(entity), message, clock.tick);
+      }
+    }
+  }
+}
+
+/// Clears paths that run over a tile something impassable just appeared on,
+/// so they get planned again around it
+pub fn invalidate_blocked_paths(
+  blocked: Query<&Position, Added<Impassable>>,
+  mut paths: Query<&mut Path>,
+) {
+  if blocked.is_empty() {
+    return;
+  }
+
+  for mut path in paths.iter_mut() {
+    if blocked.iter().any(|position| path.path.contains(position)) {
+      path.path.clear();
     }
   }
-}
\ No newline at end of file
+}
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::grid::{Grid, Position};
+use crate::archetype::{ChargerDefinition, PowerDefinition};
+use crate::entities::building::Locked;
+use crate::simulation::SimulationClock;
+use crate::lighting;
+
+/// A building on the power grid. Generators supply `output`, consumers ask
+/// for `draw`, and conduits do neither but connect the tiles either side.
+#[derive(Component, Debug)]
+pub struct PowerNode {
+  pub output: u32,
+  pub draw: u32,
+  /// Whether the output only comes while the sun is up
+  pub solar: bool,
+  /// Share of the network's demand that supply covers, in `0.0..=1.0`
+  pub satisfaction: f32,
+}
+
+impl PowerNode {
+  pub fn new(definition: PowerDefinition) -> Self {
+    Self { output: definition.output, draw: definition.draw, solar: definition.solar, satisfaction: 1.0 }
+  }
+
+  /// How fast work at the building goes, in `0.0..=1.0`. Only consumers slow down.
+  pub fn rate(&self) -> f32 {
+    if self.draw == 0 { 1.0 } else { self.satisfaction }
+  }
+}
+
+/// A station bots recharge their batteries at
+#[derive(Component)]
+pub struct Charger(pub ChargerDefinition);
+
+/// Buildings joined into one network by sharing edges
+#[derive(Debug)]
+pub struct PowerNetwork {
+  pub tiles: Vec<Position>,
+  pub supply: u32,
+  pub demand: u32,
+}
+
+impl PowerNetwork {
+  pub fn satisfaction(&self) -> f32 {
+    if self.demand == 0 {
+      1.0
+    } else {
+      (self.supply as f32 / self.demand as f32).min(1.0)
+    }
+  }
+}
+
+/// The networks found on the last tick, and whether the P overlay shows them
+#[derive(Resource, Default)]
+pub struct PowerGrid {
+  pub networks: Vec<PowerNetwork>,
+  pub overlay: bool,
+}
+
+impl PowerGrid {
+  pub fn supply(&self) -> u32 {
+    self.networks.iter().map(|network| network.supply).sum()
+  }
+
+  pub fn demand(&self) -> u32 {
+    self.networks.iter().map(|network| network.demand).sum()
+  }
+}
+
+/// Groups power nodes into networks of orthogonally adjacent tiles and shares
+/// each network's supply out over its demand. Locked buildings neither supply
+/// nor draw, and solar generators supply as much of their output as the sun
+/// allows.
+pub fn balance_power(
+  grid: Res<Grid>,
+  clock: Res<SimulationClock>,
+  mut power: ResMut<PowerGrid>,
+  mut nodes: Query<(Entity, &Position, &mut PowerNode, Has<Locked>)>,
+) {
+  // A conduit can run under a consumer, so a tile can hold several nodes
+  let mut by_tile: HashMap<Position, Vec<Entity>> = HashMap::new();
+  for (entity, position, ..) in nodes.iter() {
+    by_tile.entry(*position).or_default().push(entity);
+  }
+  let mut visited: HashSet<Position> = HashSet::new();
+  let mut networks = Vec::new();
+  let sunlight = lighting::sunlight(&clock);
+
+  for (_, start, ..) in nodes.iter() {
+    if !visited.insert(*start) {
+      continue;
+    }
+
+    let mut network = PowerNetwork { tiles: Vec::new(), supply: 0, demand: 0 };
+    let mut queue = VecDeque::from([*start]);
+    while let Some(position) = queue.pop_front() {
+      for (_, _, node, locked) in by_tile.get(&position).into_iter().flatten().filter_map(|entity| nodes.get(*entity).ok()) {
+        if locked {
+          continue;
+        }
+        network.supply += if node.solar { (node.output as f32 * sunlight).round() as u32 } else { node.output };
+        network.demand += node.draw;
+      }
+      network.tiles.push(position);
+
+      for neighbor in grid.neighbors(&position) {
+        if by_tile.contains_key(&neighbor) && visited.insert(neighbor) {
+          queue.push_back(neighbor);
+        }
+      }
+    }
+    networks.push(network);
+  }
+
+  for network in &networks {
+    let satisfaction = network.satisfaction();
+    for entity in network.tiles.iter().filter_map(|position| by_tile.get(position)).flatten() {
+      if let Ok((_, _, mut node, _)) = nodes.get_mut(*entity) {
+        node.satisfaction = satisfaction;
+      }
+    }
+  }
+
+  power.networks = networks;
+}
+
+pub fn toggle_power_overlay(keys: Res<ButtonInput<KeyCode>>, mut power: ResMut<PowerGrid>) {
+  if keys.just_pressed(KeyCode::KeyP) {
+    power.overlay = !power.overlay;
+  }
+}
+
+/// Links the buildings of each network, green when demand is met, amber when
+/// consumers are slowed and red when they have stopped
+pub fn draw_power_overlay(grid: Res<Grid>, power: Res<PowerGrid>, mut gizmos: Gizmos) {
+  if !power.overlay {
+    return;
+  }
+
+  for network in &power.networks {
+    let satisfaction = network.satisfaction();
+    let color = if satisfaction >= 1.0 {
+      Color::srgb(0.3, 0.9, 0.3)
+    } else if satisfaction > 0.0 {
+      Color::srgb(1.0, 0.7, 0.1)
+    } else {
+      Color::srgb(1.0, 0.2, 0.2)
+    };
+
+    let tiles: HashSet<&Position> = network.tiles.iter().collect();
+    for position in &network.tiles {
+      let center = grid.world_position(position);
+      gizmos.circle_2d(center, grid.tile_size * 0.15, color);
+      // Each link once, from its left or lower end
+      for neighbor in [Position::new(position.x + 1, position.y), Position::new(position.x, position.y + 1)] {
+        if tiles.contains(&neighbor) {
+          gizmos.line_2d(center, grid.world_position(&neighbor), color);
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bevy::ecs::system::RunSystemOnce;
+  use crate::grid::{GRID_WIDTH, GRID_HEIGHT, TILE_SIZE};
+
+  fn network(supply: u32, demand: u32) -> PowerNetwork {
+    PowerNetwork { tiles: Vec::new(), supply, demand }
+  }
+
+  #[test]
+  fn satisfaction_is_the_share_of_demand_supplied() {
+    assert_eq!(network(0, 0).satisfaction(), 1.0);
+    assert_eq!(network(60, 40).satisfaction(), 1.0);
+    assert_eq!(network(30, 40).satisfaction(), 0.75);
+    assert_eq!(network(0, 40).satisfaction(), 0.0);
+  }
+
+  #[test]
+  fn locked_generators_supply_nothing() {
+    let mut world = World::new();
+    world.insert_resource(Grid::new(GRID_WIDTH, GRID_HEIGHT, TILE_SIZE));
+    world.init_resource::<SimulationClock>();
+    world.init_resource::<PowerGrid>();
+    world.spawn((Position::new(2, 2), PowerNode::new(PowerDefinition { output: 60, draw: 0, solar: false }), Locked));
+    world.spawn((Position::new(3, 2), PowerNode::new(PowerDefinition { output: 0, draw: 20, solar: false })));
+    world.run_system_once(balance_power);
+
+    let power = world.resource::<PowerGrid>();
+    assert_eq!((power.supply(), power.demand()), (0, 20));
+  }
+
+  #[test]
+  fn nodes_that_draw_nothing_run_at_full_rate() {
+    let mut conduit = PowerNode::new(PowerDefinition { output: 0, draw: 0, solar: false });
+    conduit.satisfaction = 0.0;
+    assert_eq!(conduit.rate(), 1.0);
+  }
+
+  /// A solar panel next to a consumer, balanced at the start of `hour`
+  fn solar_network(hour: u64) -> World {
+    let mut world = World::new();
+    world.insert_resource(Grid::new(GRID_WIDTH, GRID_HEIGHT, TILE_SIZE));
+    world.insert_resource(SimulationClock::at_hour(hour));
+    world.init_resource::<PowerGrid>();
+    world.spawn((Position::new(2, 2), PowerNode::new(PowerDefinition { output: 30, draw: 0, solar: true })));
+    world.spawn((Position::new(3, 2), PowerNode::new(PowerDefinition { output: 0, draw: 20, solar: false })));
+    world.run_system_once(balance_power);
+    world
+  }
+
+  #[test]
+  fn solar_panels_only_supply_while_the_sun_is_up() {
+    let noon = solar_network(12);
+    assert_eq!(noon.resource::<PowerGrid>().supply(), 30);
+    assert_eq!(noon.resource::<PowerGrid>().networks[0].satisfaction(), 1.0);
+
+    let night = solar_network(0);
+    assert_eq!(night.resource::<PowerGrid>().supply(), 0);
+    assert_eq!(night.resource::<PowerGrid>().networks[0].satisfaction(), 0.0);
+  }
+}
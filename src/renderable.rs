@@ -2,6 +2,10 @@ use bevy::prelude::*;
 use std::collections::HashMap;
 use crate::grid::{Position, Grid};
 use crate::interact::Interaction;
+use crate::movement::{PreviousPosition, Facing, MovementSpeed};
+use crate::atlas::AnimationSet;
+
+const TURN_SPEED: f32 = 4.0 * std::f32::consts::PI;
 
 #[derive(Resource, Default)]
 pub struct SpriteMapping {
@@ -11,16 +15,59 @@ pub struct SpriteMapping {
 #[derive(Component)]
 pub struct Renderable {
   color: Color,
-  sprite_entity: Option<Entity>
+  sprite_entity: Option<Entity>,
+  rotate_to_facing: bool,
+  animations: Option<AnimationSet>,
+  /// Sprite size as a fraction of a tile
+  scale: f32,
+  z: f32,
 }
 
 impl Renderable {
   pub fn new(r: f32, g: f32, b: f32) -> Self {
     Self {
       color: Color::srgb(r, g, b),
-      sprite_entity: None
+      sprite_entity: None,
+      rotate_to_facing: false,
+      animations: None,
+      scale: 1.0,
+      z: 1.0,
     }
   }
+
+  /// Draw smaller and below bots and buildings, for things lying on the floor
+  pub fn on_floor(mut self, scale: f32) -> Self {
+    self.scale = scale;
+    self.z = 0.5;
+    self
+  }
+
+  /// Draw from the sprite atlas when it is available, keeping the color as a fallback
+  pub fn with_animations(mut self, animations: AnimationSet) -> Self {
+    self.animations = Some(animations);
+    self
+  }
+
+  /// Takes over the look of another renderable while keeping the spawned sprite
+  pub fn restyle(&mut self, other: Renderable) {
+    self.color = other.color;
+    self.rotate_to_facing = other.rotate_to_facing;
+    self.animations = other.animations;
+  }
+
+  pub fn sprite_entity(&self) -> Option<Entity> {
+    self.sprite_entity
+  }
+
+  pub fn animations(&self) -> Option<AnimationSet> {
+    self.animations
+  }
+
+  /// Turn the sprite toward the entity's `Facing` as it moves
+  pub fn with_rotation(mut self) -> Self {
+    self.rotate_to_facing = true;
+    self
+  }
 }
 
 pub fn spawn_sprites_for_new_renderables(
@@ -30,37 +77,95 @@ pub fn spawn_sprites_for_new_renderables(
   mut query: Query<(Entity, &mut Renderable, &Position), Added<Renderable>>
 ) {
   for (entity, mut renderable, position) in query.iter_mut() {
-    let world_x = ((position.x as f32 - grid.width as f32 / 2.0) + 0.5) * grid.tile_size;
-    let world_y = ((position.y as f32 - grid.height as f32 / 2.0) + 0.5) * grid.tile_size;
+    let world = grid.world_position(position);
 
     let sprite_entity = commands.spawn(SpriteBundle {
       sprite: Sprite {
         color: renderable.color,
-        custom_size: Some(Vec2::new(grid.tile_size, grid.tile_size)),
+        custom_size: Some(Vec2::splat(grid.tile_size * renderable.scale)),
         ..default()
       },
-      transform: Transform::from_xyz(world_x, world_y, 1.0),
+      transform: Transform::from_xyz(world.x, world.y, renderable.z),
       ..default()
     }).id();
 
+    commands.entity(entity).insert(PreviousPosition(*position));
+    if renderable.rotate_to_facing {
+      commands.entity(entity).insert(Facing::default());
+    }
+
     renderable.sprite_entity = Some(sprite_entity);
     sprite_mapping.entity_to_sprite.insert(entity, sprite_entity);
   }
 }
 
+/// Places sprites between the previous and current tile using the fixed
+/// timestep overstep, so movement reads smoothly at any tick rate.
 pub fn update_sprite_positions(
   grid: Res<Grid>,
-  query: Query<(&Renderable, &Position), Changed<Position>>,
+  fixed_time: Res<Time<Fixed>>,
+  query: Query<(&Renderable, &Position, Option<&PreviousPosition>, Option<&MovementSpeed>)>,
+  mut transforms: Query<&mut Transform>
+) {
+  let overstep = fixed_time.overstep_fraction().clamp(0.0, 1.0);
+
+  for (renderable, position, previous, speed) in query.iter() {
+    let Some(sprite_entity) = renderable.sprite_entity else {
+      continue;
+    };
+
+    if let Ok(mut transform) = transforms.get_mut(sprite_entity) {
+      let current = grid.world_position(position);
+      let alpha = speed.map_or(overstep, |speed| speed.step_fraction(overstep));
+      let world = match previous {
+        Some(previous) => grid.world_position(&previous.0).lerp(current, alpha),
+        None => current,
+      };
+
+      transform.translation.x = world.x;
+      transform.translation.y = world.y;
+    }
+  }
+}
+
+pub fn rotate_sprites_to_facing(
+  time: Res<Time>,
+  query: Query<(&Renderable, &Facing)>,
   mut transforms: Query<&mut Transform>
 ) {
-  for (renderable, position) in query.iter() {
-    if let Some(sprite_entity) = renderable.sprite_entity {
-      if let Ok(mut transform) = transforms.get_mut(sprite_entity) {
-        let world_x = ((position.x as f32 - grid.width as f32 / 2.0) + 0.5) * grid.tile_size;
-        let world_y = ((position.y as f32 - grid.height as f32 / 2.0) + 0.5) * grid.tile_size;
-
-        transform.translation.x = world_x;
-        transform.translation.y = world_y;
+  for (renderable, facing) in query.iter() {
+    if !renderable.rotate_to_facing {
+      continue;
+    }
+
+    let Some(sprite_entity) = renderable.sprite_entity else {
+      continue;
+    };
+
+    if let Ok(mut transform) = transforms.get_mut(sprite_entity) {
+      let target = Quat::from_rotation_arc_2d(Vec2::Y, facing.0);
+      let remaining = transform.rotation.angle_between(target);
+      if remaining > f32::EPSILON {
+        let step = (TURN_SPEED * time.delta_seconds() / remaining).min(1.0);
+        transform.rotation = transform.rotation.slerp(target, step);
+      }
+    }
+  }
+}
+
+/// Keeps fallback colored squares in sync after a renderable is restyled
+pub fn sync_sprite_colors(
+  query: Query<&Renderable, Changed<Renderable>>,
+  mut sprites: Query<&mut Sprite, Without<TextureAtlas>>,
+) {
+  for renderable in query.iter() {
+    let Some(sprite_entity) = renderable.sprite_entity else {
+      continue;
+    };
+
+    if let Ok(mut sprite) = sprites.get_mut(sprite_entity) {
+      if sprite.color != renderable.color {
+        sprite.color = renderable.color;
       }
     }
   }
@@ -98,16 +203,15 @@ pub fn draw_interaction_progress_bars(
     if let Ok(position) = positions.get(interaction.actor) {
       let progress = interaction.ticks_completed as f32 / interaction.ticks_to_complete as f32;
 
-      let world_x = ((position.x as f32 - grid.width as f32 / 2.0) + 0.5) * grid.tile_size;
-      let world_y = ((position.y as f32 - grid.height as f32 / 2.0) + 0.5) * grid.tile_size;
+      let world = grid.world_position(position);
 
       if let Some(bar_entity) = interaction.progress_bar_entity {
         if let Ok(mut sprite) = sprites.get_mut(bar_entity) {
           sprite.custom_size = Some(Vec2::new(grid.tile_size * progress, 5.0));
         }
         if let Ok(mut transform) = transforms.get_mut(bar_entity) {
-          transform.translation.x = world_x;
-          transform.translation.y = world_y + grid.tile_size * 0.6;
+          transform.translation.x = world.x;
+          transform.translation.y = world.y + grid.tile_size * 0.6;
         }
       } else {
         let bar_entity = commands.spawn(SpriteBundle {
@@ -116,7 +220,7 @@ pub fn draw_interaction_progress_bars(
             custom_size: Some(Vec2::new(grid.tile_size * progress, 5.0)),
             ..default()
           },
-          transform: Transform::from_xyz(world_x, world_y + grid.tile_size * 0.6, 2.0),
+          transform: Transform::from_xyz(world.x, world.y + grid.tile_size * 0.6, 2.0),
           ..default()
         }).id();
 
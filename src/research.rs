@@ -0,0 +1,347 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+use crate::archetype::{Archetype, Archetypes, TechDefinition, Unlock};
+use crate::entities::building::{Building, Locked};
+use crate::hud::{text_style, BUTTON_IDLE, BUTTON_HOVERED};
+
+/// Research progress of the colony
+#[derive(Resource, Default)]
+pub struct Research {
+  pub completed: HashSet<String>,
+  /// Techs waiting to be researched, front first. Every tech's prerequisites
+  /// are completed or queued ahead of it.
+  pub queue: VecDeque<String>,
+  /// Points put into each tech so far, kept when it is dequeued
+  pub progress: HashMap<String, u32>,
+}
+
+impl Research {
+  /// The tech research points currently go to
+  pub fn current(&self) -> Option<&str> {
+    self.queue.front().map(|name| name.as_str())
+  }
+
+  /// Whether no unresearched tech still locks `unlock`
+  pub fn is_unlocked(&self, unlock: &Unlock, archetypes: &Archetypes) -> bool {
+    archetypes.library.techs.iter()
+      .all(|(name, tech)| self.completed.contains(name) || !tech.unlocks.contains(unlock))
+  }
+
+  pub fn recipe_unlocked(&self, name: &str, archetypes: &Archetypes) -> bool {
+    self.is_unlocked(&Unlock::Recipe(name.to_string()), archetypes)
+  }
+
+  pub fn module_unlocked(&self, name: &str, archetypes: &Archetypes) -> bool {
+    self.is_unlocked(&Unlock::Module(name.to_string()), archetypes)
+  }
+
+  /// Queues a tech, or dequeues it along with the queued techs that depend on it
+  pub fn toggle(&mut self, name: &str, archetypes: &Archetypes) {
+    if self.completed.contains(name) {
+      return;
+    }
+
+    if self.queue.iter().any(|queued| queued == name) {
+      self.queue.retain(|queued| queued != name);
+      self.prune_queue(archetypes);
+    } else if archetypes.tech(name).is_some_and(|tech| self.prerequisites_planned(tech)) {
+      self.queue.push_back(name.to_string());
+    }
+  }
+
+  /// Adds points to the current tech, completing it once its cost is reached
+  pub fn add_points(&mut self, points: u32, archetypes: &Archetypes) {
+    let Some(name) = self.queue.front().cloned() else {
+      return;
+    };
+    let Some(tech) = archetypes.tech(&name) else {
+      self.queue.pop_front();
+      return;
+    };
+
+    let progress = self.progress.entry(name.clone()).or_insert(0);
+    *progress += points;
+    if *progress >= tech.cost {
+      info!("Researched {}", name);
+      self.queue.pop_front();
+      self.progress.remove(&name);
+      self.completed.insert(name);
+    }
+  }
+
+  fn prerequisites_planned(&self, tech: &TechDefinition) -> bool {
+    tech.prerequisites.iter()
+      .all(|prerequisite| self.completed.contains(prerequisite) || self.queue.contains(prerequisite))
+  }
+
+  /// Drops queued techs whose prerequisites are no longer ahead of them
+  fn prune_queue(&mut self, archetypes: &Archetypes) {
+    let mut planned = self.completed.clone();
+    self.queue.retain(|name| {
+      let ready = archetypes.tech(name).is_some_and(|tech| {
+        tech.prerequisites.iter().all(|prerequisite| planned.contains(prerequisite))
+      });
+      if ready {
+        planned.insert(name.clone());
+      }
+      ready
+    });
+  }
+}
+
+/// Marks buildings whose archetype is still locked by research so they stay inert
+pub fn lock_buildings(
+  mut commands: Commands,
+  research: Res<Research>,
+  archetypes: Res<Archetypes>,
+  buildings: Query<(Entity, &Archetype, Has<Locked>), With<Building>>,
+  added: Query<(), Added<Building>>,
+) {
+  if !research.is_changed() && !archetypes.is_changed() && added.is_empty() {
+    return;
+  }
+
+  for (entity, archetype, locked) in buildings.iter() {
+    let unlocked = research.is_unlocked(&Unlock::Building(archetype.0.clone()), &archetypes);
+    if unlocked && locked {
+      commands.entity(entity).remove::<Locked>();
+    } else if !unlocked && !locked {
+      commands.entity(entity).insert(Locked);
+    }
+  }
+}
+
+#[derive(Component)]
+pub struct ResearchScreen;
+
+#[derive(Component)]
+pub struct ResearchStatusText;
+
+/// Holds the tech buttons, rebuilt whenever the tech tree changes
+#[derive(Component)]
+pub struct TechList;
+
+#[derive(Component)]
+pub struct TechButton(pub String);
+
+pub fn spawn_research_screen(mut commands: Commands) {
+  commands.spawn((
+    NodeBundle {
+      style: Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(8.0),
+        left: Val::Percent(30.0),
+        flex_direction: FlexDirection::Column,
+        row_gap: Val::Px(4.0),
+        padding: UiRect::all(Val::Px(8.0)),
+        ..default()
+      },
+      background_color: Color::srgba(0.0, 0.0, 0.0, 0.8).into(),
+      visibility: Visibility::Hidden,
+      ..default()
+    },
+    ResearchScreen,
+    Interaction::default(),
+  )).with_children(|screen| {
+    screen.spawn(TextBundle::from_section("Research (T to close, click to queue)", text_style(Color::WHITE)));
+    screen.spawn((TextBundle::from_section("", text_style(Color::srgb(0.7, 0.7, 0.7))), ResearchStatusText));
+    screen.spawn((
+      NodeBundle {
+        style: Style {
+          flex_direction: FlexDirection::Column,
+          row_gap: Val::Px(2.0),
+          ..default()
+        },
+        ..default()
+      },
+      TechList,
+    ));
+  });
+}
+
+pub fn toggle_research_screen(
+  keys: Res<ButtonInput<KeyCode>>,
+  mut screens: Query<&mut Visibility, With<ResearchScreen>>,
+) {
+  if !keys.just_pressed(KeyCode::KeyT) {
+    return;
+  }
+
+  for mut visibility in screens.iter_mut() {
+    *visibility = match *visibility {
+      Visibility::Hidden => Visibility::Inherited,
+      _ => Visibility::Hidden,
+    };
+  }
+}
+
+/// Depth of a tech in the tree, so prerequisites are listed above what they lead to
+fn tech_depth(name: &str, archetypes: &Archetypes, seen: &mut Vec<String>) -> usize {
+  if seen.iter().any(|visited| visited == name) {
+    return 0;
+  }
+  seen.push(name.to_string());
+
+  let depth = archetypes.tech(name).map_or(0, |tech| {
+    tech.prerequisites.iter()
+      .map(|prerequisite| tech_depth(prerequisite, archetypes, seen) + 1)
+      .max()
+      .unwrap_or(0)
+  });
+  seen.pop();
+  depth
+}
+
+pub fn rebuild_tech_list(
+  mut commands: Commands,
+  archetypes: Res<Archetypes>,
+  lists: Query<Entity, With<TechList>>,
+) {
+  if !archetypes.is_changed() {
+    return;
+  }
+
+  let mut techs: Vec<(usize, &String)> = archetypes.library.techs.keys()
+    .map(|name| (tech_depth(name, &archetypes, &mut Vec::new()), name))
+    .collect();
+  techs.sort();
+
+  for list in lists.iter() {
+    commands.entity(list).despawn_descendants().with_children(|list| {
+      for (depth, name) in &techs {
+        list.spawn((
+          ButtonBundle {
+            style: Style {
+              margin: UiRect::left(Val::Px(16.0 * *depth as f32)),
+              padding: UiRect::axes(Val::Px(6.0), Val::Px(2.0)),
+              ..default()
+            },
+            background_color: BUTTON_IDLE.into(),
+            ..default()
+          },
+          TechButton(name.to_string()),
+        )).with_children(|button| {
+          button.spawn(TextBundle::from_section("", text_style(Color::WHITE)));
+        });
+      }
+    });
+  }
+}
+
+pub fn handle_tech_buttons(
+  archetypes: Res<Archetypes>,
+  mut research: ResMut<Research>,
+  mut buttons: Query<(&Interaction, &TechButton, &mut BackgroundColor), Changed<Interaction>>,
+) {
+  for (interaction, button, mut background) in buttons.iter_mut() {
+    match interaction {
+      Interaction::Pressed => research.toggle(&button.0, &archetypes),
+      Interaction::Hovered => *background = BUTTON_HOVERED.into(),
+      Interaction::None => *background = BUTTON_IDLE.into(),
+    }
+  }
+}
+
+fn describe_unlock(unlock: &Unlock) -> String {
+  match unlock {
+    Unlock::Building(name) => format!("building {}", name),
+    Unlock::Module(name) => format!("module {}", name),
+    Unlock::Recipe(name) => format!("recipe {}", name),
+  }
+}
+
+pub fn update_research_screen(
+  archetypes: Res<Archetypes>,
+  research: Res<Research>,
+  buttons: Query<(&TechButton, &Children)>,
+  added: Query<(), Added<TechButton>>,
+  mut status_texts: Query<&mut Text, With<ResearchStatusText>>,
+  mut texts: Query<&mut Text, Without<ResearchStatusText>>,
+) {
+  if !research.is_changed() && !archetypes.is_changed() && added.is_empty() {
+    return;
+  }
+
+  let status = match research.current() {
+    Some(name) => format!(
+      "Researching {} ({}/{}), {} queued",
+      name,
+      research.progress.get(name).copied().unwrap_or(0),
+      archetypes.tech(name).map_or(0, |tech| tech.cost),
+      research.queue.len() - 1,
+    ),
+    None => "Nothing queued".to_string(),
+  };
+  for mut text in status_texts.iter_mut() {
+    text.sections[0].value = status.clone();
+  }
+
+  for (button, children) in buttons.iter() {
+    let Some(tech) = archetypes.tech(&button.0) else {
+      continue;
+    };
+
+    let (state, color) = if research.completed.contains(&button.0) {
+      ("done".to_string(), Color::srgb(0.5, 0.8, 0.5))
+    } else if let Some(position) = research.queue.iter().position(|queued| *queued == button.0) {
+      (format!("queued #{}", position + 1), Color::srgb(1.0, 0.8, 0.3))
+    } else if tech.prerequisites.iter().all(|prerequisite| research.completed.contains(prerequisite)) {
+      ("available".to_string(), Color::WHITE)
+    } else {
+      (format!("needs {}", tech.prerequisites.join(", ")), Color::srgb(0.5, 0.5, 0.5))
+    };
+
+    let unlocks = tech.unlocks.iter().map(describe_unlock).collect::<Vec<_>>().join(", ");
+    let label = format!("{} [{}] cost {}: {}", button.0, state, tech.cost, unlocks);
+
+    for child in children.iter() {
+      if let Ok(mut text) = texts.get_mut(*child) {
+        text.sections[0].value = label.clone();
+        text.sections[0].style.color = color;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn queued(archetypes: &Archetypes, names: &[&str]) -> Research {
+    let mut research = Research::default();
+    for name in names {
+      research.toggle(name, archetypes);
+    }
+    research
+  }
+
+  #[test]
+  fn techs_queue_only_behind_their_prerequisites() {
+    let archetypes = Archetypes::builtin();
+    let research = queued(&archetypes, &["drilling", "electronics", "drilling"]);
+    assert_eq!(research.queue, ["electronics", "drilling"]);
+  }
+
+  #[test]
+  fn dequeuing_a_tech_drops_what_depends_on_it() {
+    let archetypes = Archetypes::builtin();
+    let mut research = queued(&archetypes, &["electronics", "drilling", "locomotion", "automation", "logistics"]);
+
+    research.toggle("drilling", &archetypes);
+    assert_eq!(research.queue, ["electronics", "locomotion", "logistics"]);
+
+    research.toggle("electronics", &archetypes);
+    assert_eq!(research.queue, ["logistics"]);
+  }
+
+  #[test]
+  fn completed_prerequisites_keep_dependents_queued() {
+    let archetypes = Archetypes::builtin();
+    let mut research = queued(&archetypes, &["electronics", "drilling"]);
+    research.add_points(20, &archetypes);
+    assert!(research.completed.contains("electronics"));
+
+    research.prune_queue(&archetypes);
+    assert_eq!(research.queue, ["drilling"]);
+  }
+}
@@ -1,33 +1,33 @@
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use bevy::prelude::*;
+use bevy::ecs::entity::Entities;
 use crate::grid::Position;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ReservationKey {
+  /// Dropping items onto a tile, or exploring from it
   Tile(Position),
   Entity(Entity),
+  /// Supplying a building's input buffer
+  Input(Entity),
+  /// Emptying a building's output buffer
+  Output(Entity),
 }
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct ReservationSystem {
   reservations: HashMap<ReservationKey, Entity>,
 }
 
-impl Default for ReservationSystem {
-  fn default() -> Self {
-    Self {
-      reservations: HashMap::new(),
-    }
-  }
-}
-
 impl ReservationSystem {
   pub fn try_reserve(&mut self, key: ReservationKey, bot_entity: Entity) -> bool {
-    if self.reservations.contains_key(&key) {
-      false  // Already reserved by another bot
-    } else {
-      self.reservations.insert(key, bot_entity);
-      true
+    match self.reservations.entry(key) {
+      Entry::Occupied(_) => false,  // Already reserved by another bot
+      Entry::Vacant(entry) => {
+        entry.insert(bot_entity);
+        true
+      }
     }
   }
 
@@ -35,12 +35,34 @@ impl ReservationSystem {
     self.reservations.remove(key);
   }
 
+  pub fn iter(&self) -> impl Iterator<Item = (&ReservationKey, Entity)> {
+    self.reservations.iter().map(|(key, bot)| (key, *bot))
+  }
+
+  /// Number of keys currently reserved
+  pub fn count(&self) -> usize {
+    self.reservations.len()
+  }
+
   pub fn is_reserved(&self, key: &ReservationKey) -> bool {
     self.reservations.contains_key(key)
   }
 
-  /// Get who reserved something
-  pub fn get_reserver(&self, key: &ReservationKey) -> Option<Entity> {
-    self.reservations.get(key).copied()
+  /// Drops every reservation held by or naming an entity `is_alive` rejects
+  pub fn release_dead(&mut self, is_alive: impl Fn(Entity) -> bool) {
+    self.reservations.retain(|key, bot| {
+      let target = match key {
+        ReservationKey::Tile(_) => None,
+        ReservationKey::Entity(target) | ReservationKey::Input(target) | ReservationKey::Output(target) => Some(*target),
+      };
+      is_alive(*bot) && target.is_none_or(&is_alive)
+    });
   }
 }
+
+/// Releases reservations of bots and targets that have been despawned. It
+/// checks every reservation rather than reading removals, so despawns made
+/// while the simulation was paused are still caught.
+pub fn release_despawned(mut reservations: ResMut<ReservationSystem>, entities: &Entities) {
+  reservations.release_dead(|entity| entities.contains(entity));
+}
@@ -0,0 +1,63 @@
+use bevy::prelude::*;
+use crate::simulation::HOURS_PER_DAY;
+
+/// What a bot does during one hour of the day
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Activity {
+  /// Take jobs as usual
+  #[default]
+  Work,
+  /// Top up the battery at a charger, then wait at home
+  Charge,
+  /// Put away what it carries and wait at home
+  Idle,
+}
+
+impl Activity {
+  pub fn next(self) -> Self {
+    match self {
+      Activity::Work => Activity::Charge,
+      Activity::Charge => Activity::Idle,
+      Activity::Idle => Activity::Work,
+    }
+  }
+
+  /// One letter for the timetable
+  pub fn letter(&self) -> &'static str {
+    match self {
+      Activity::Work => "W",
+      Activity::Charge => "C",
+      Activity::Idle => "I",
+    }
+  }
+
+  pub fn color(&self) -> Color {
+    match self {
+      Activity::Work => Color::srgb(0.2, 0.35, 0.5),
+      Activity::Charge => Color::srgb(0.5, 0.45, 0.15),
+      Activity::Idle => Color::srgb(0.25, 0.25, 0.25),
+    }
+  }
+}
+
+/// An activity for each hour of the day
+#[derive(Clone, Debug)]
+pub struct Schedule(pub [Activity; HOURS_PER_DAY as usize]);
+
+impl Default for Schedule {
+  fn default() -> Self {
+    Self([Activity::Work; HOURS_PER_DAY as usize])
+  }
+}
+
+impl Schedule {
+  pub fn at(&self, hour: usize) -> Activity {
+    self.0.get(hour).copied().unwrap_or_default()
+  }
+
+  pub fn cycle(&mut self, hour: usize) {
+    if let Some(activity) = self.0.get_mut(hour) {
+      *activity = activity.next();
+    }
+  }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use bevy::app::FixedMain;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+pub const BASE_TICK_HZ: f64 = 10.0;
+/// Fixed ticks in one in-game hour
+pub const TICKS_PER_HOUR: u64 = 100;
+pub const HOURS_PER_DAY: u64 = 24;
+/// Hour of day one the simulation starts at
+const START_HOUR: u64 = 8;
+/// Seed of every random draw the simulation makes, so a run can be replayed
+const SIMULATION_SEED: u64 = 0x5eed_e7e7;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulationSpeed {
+  Normal,
+  Double,
+  Fast,
+  Max,
+}
+
+impl SimulationSpeed {
+  pub fn multiplier(&self) -> f64 {
+    match self {
+      SimulationSpeed::Normal => 1.0,
+      SimulationSpeed::Double => 2.0,
+      SimulationSpeed::Fast => 5.0,
+      SimulationSpeed::Max => 20.0,
+    }
+  }
+
+  pub fn label(&self) -> &'static str {
+    match self {
+      SimulationSpeed::Normal => "1x",
+      SimulationSpeed::Double => "2x",
+      SimulationSpeed::Fast => "5x",
+      SimulationSpeed::Max => "MAX",
+    }
+  }
+}
+
+#[derive(Resource)]
+pub struct SimulationControl {
+  pub speed: SimulationSpeed,
+  pub paused: bool,
+  /// Set for the frame in which a single tick was stepped while paused
+  pub stepped: bool,
+  step_requested: bool,
+}
+
+impl Default for SimulationControl {
+  fn default() -> Self {
+    Self {
+      speed: SimulationSpeed::Normal,
+      paused: false,
+      stepped: false,
+      step_requested: false,
+    }
+  }
+}
+
+/// Counts fixed ticks since the simulation started
+#[derive(Resource, Default)]
+pub struct SimulationClock {
+  pub tick: u64,
+}
+
+impl SimulationClock {
+  /// Ticks since midnight of day one, given the colony lands in the morning
+  fn day_ticks(&self) -> u64 {
+    self.tick + START_HOUR * TICKS_PER_HOUR
+  }
+
+  /// Hour of the in-game day, counting from midnight
+  pub fn hour(&self) -> usize {
+    (self.day_ticks() / TICKS_PER_HOUR % HOURS_PER_DAY) as usize
+  }
+
+  /// Hours since midnight, including the fraction of the current hour
+  pub fn time_of_day(&self) -> f32 {
+    (self.day_ticks() % (TICKS_PER_HOUR * HOURS_PER_DAY)) as f32 / TICKS_PER_HOUR as f32
+  }
+
+  /// Day number, starting at 1
+  pub fn day(&self) -> u64 {
+    self.day_ticks() / TICKS_PER_HOUR / HOURS_PER_DAY + 1
+  }
+}
+
+#[cfg(test)]
+impl SimulationClock {
+  /// The first tick that falls on the start of `hour`
+  pub fn at_hour(hour: u64) -> Self {
+    Self { tick: (HOURS_PER_DAY + hour - START_HOUR) % HOURS_PER_DAY * TICKS_PER_HOUR }
+  }
+}
+
+/// The one source of randomness for gameplay. Systems that draw from it run
+/// in a fixed order, so the same seed always plays out the same way.
+#[derive(Resource)]
+pub struct SimulationRng(pub StdRng);
+
+impl Default for SimulationRng {
+  fn default() -> Self {
+    Self(StdRng::seed_from_u64(SIMULATION_SEED))
+  }
+}
+
+#[derive(Component)]
+pub struct SpeedIndicator;
+
+/// Run condition for gameplay systems outside of `FixedUpdate`
+pub fn is_running(control: Res<SimulationControl>) -> bool {
+  !control.paused || control.stepped
+}
+
+pub fn advance_clock(mut clock: ResMut<SimulationClock>) {
+  clock.tick += 1;
+}
+
+pub fn handle_speed_input(
+  keys: Res<ButtonInput<KeyCode>>,
+  mut control: ResMut<SimulationControl>,
+  mut virtual_time: ResMut<Time<Virtual>>,
+  mut fixed_time: ResMut<Time<Fixed>>,
+) {
+  if control.stepped {
+    control.stepped = false;
+  }
+
+  if keys.just_pressed(KeyCode::Space) {
+    control.paused = !control.paused;
+  }
+
+  if keys.just_pressed(KeyCode::Period) {
+    control.paused = true;
+    control.step_requested = true;
+  }
+
+  let speed = if keys.just_pressed(KeyCode::Digit1) {
+    Some(SimulationSpeed::Normal)
+  } else if keys.just_pressed(KeyCode::Digit2) {
+    Some(SimulationSpeed::Double)
+  } else if keys.just_pressed(KeyCode::Digit3) {
+    Some(SimulationSpeed::Fast)
+  } else if keys.just_pressed(KeyCode::Digit4) {
+    Some(SimulationSpeed::Max)
+  } else {
+    None
+  };
+
+  if let Some(speed) = speed {
+    control.speed = speed;
+    fixed_time.set_timestep_hz(BASE_TICK_HZ * speed.multiplier());
+  }
+
+  if control.paused && !virtual_time.is_paused() {
+    virtual_time.pause();
+  } else if !control.paused && virtual_time.is_paused() {
+    virtual_time.unpause();
+  }
+}
+
+/// Runs exactly one fixed tick while paused. Virtual time stays paused, so the
+/// regular fixed loop does not accumulate anything on its own.
+pub fn step_simulation(world: &mut World) {
+  let mut control = world.resource_mut::<SimulationControl>();
+  if !control.step_requested {
+    return;
+  }
+  control.step_requested = false;
+  control.stepped = true;
+
+  world.run_schedule(FixedMain);
+}
+
+pub fn spawn_speed_indicator(mut commands: Commands) {
+  commands.spawn((
+    TextBundle::from_section("", TextStyle {
+      font_size: 18.0,
+      color: Color::WHITE,
+      ..default()
+    }).with_style(Style {
+      position_type: PositionType::Absolute,
+      top: Val::Px(8.0),
+      right: Val::Px(8.0),
+      ..default()
+    }),
+    SpeedIndicator,
+  ));
+}
+
+pub fn update_speed_indicator(
+  control: Res<SimulationControl>,
+  clock: Res<SimulationClock>,
+  mut indicators: Query<&mut Text, With<SpeedIndicator>>,
+) {
+  if !control.is_changed() && !clock.is_changed() {
+    return;
+  }
+
+  let speed = if control.paused {
+    format!("PAUSED ({})", control.speed.label())
+  } else {
+    control.speed.label().to_string()
+  };
+  for mut text in indicators.iter_mut() {
+    text.sections[0].value = format!("Day {} {:02}:00  {}", clock.day(), clock.hour(), speed);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn the_colony_lands_in_the_morning_of_day_one() {
+    let clock = SimulationClock::default();
+    assert_eq!(clock.hour(), START_HOUR as usize);
+    assert_eq!(clock.day(), 1);
+  }
+
+  #[test]
+  fn midnight_rolls_over_to_the_next_day() {
+    let until_midnight = (HOURS_PER_DAY - START_HOUR) * TICKS_PER_HOUR;
+    let before = SimulationClock { tick: until_midnight - 1 };
+    assert_eq!(before.hour(), HOURS_PER_DAY as usize - 1);
+    assert_eq!(before.day(), 1);
+
+    let after = SimulationClock { tick: until_midnight };
+    assert_eq!(after.hour(), 0);
+    assert_eq!(after.day(), 2);
+    assert_eq!(after.time_of_day(), 0.0);
+  }
+}
@@ -1,29 +1,357 @@
 use bevy::prelude::*;
-use crate::grid::{Position, Grid};
+use crate::grid::Position;
 use crate::renderable::Renderable;
 use crate::entities::scrap::Scrap;
 use crate::entities::bot::Bot;
 use crate::grid::Impassable;
+use crate::movement::MovementSpeed;
+use crate::modules::{Modules, BotStats};
+use crate::energy::Energy;
+use crate::zones::StockpileZone;
+use crate::groups::Group;
+use crate::impass::Door;
+use crate::health::Health;
+use crate::hostiles::Turret;
+use crate::spawners::ResourceSpawner;
+use crate::power::{PowerNode, Charger};
+use crate::conveyors::{Conveyor, Inserter, Heading};
+use crate::lighting::Lamp;
+use crate::entities::building::{Building, Blueprint, Fabricator, BotFactory, Workshop, Lab};
+use crate::inventory::{Inventory, Cargo, Storage, InputBuffer, OutputBuffer};
+use crate::archetype::{Archetype, Archetypes, BotDefinition, BuildingDefinition, ResourceDefinition, SpawnerDefinition};
 
-pub fn spawn_initial_components(mut commands: Commands, grid: Res<Grid>) {
-  spawn_scrap(&mut commands, 5, 15);
+pub fn spawn_initial_components(mut commands: Commands, archetypes: Res<Archetypes>) {
+  spawn_resource(&mut commands, &archetypes, "scrap", 5, 15);
+  spawn_resource(&mut commands, &archetypes, "copper", 14, 15);
+  spawn_resource(&mut commands, &archetypes, "silicon", 17, 4);
+  spawn_resource(&mut commands, &archetypes, "crystal", 2, 3);
+  spawn_spawner(&mut commands, &archetypes, "junk_pile", 3, 16);
+  spawn_spawner(&mut commands, &archetypes, "drop_zone", 16, 15);
 
-  spawn_bot(&mut commands, 15, 5);
+  spawn_building(&mut commands, &archetypes, "depot", 9, 10);
+  spawn_building(&mut commands, &archetypes, "fabricator", 11, 10);
+  spawn_building(&mut commands, &archetypes, "bot_factory", 9, 8);
+  spawn_building(&mut commands, &archetypes, "workshop", 11, 8);
+  spawn_building(&mut commands, &archetypes, "lab", 13, 10);
+  spawn_building(&mut commands, &archetypes, "generator", 12, 9);
+  spawn_building(&mut commands, &archetypes, "charger", 14, 9);
+  for x in [9, 10, 11, 13] {
+    spawn_building(&mut commands, &archetypes, "conduit", x, 9);
+  }
+
+  commands.spawn(StockpileZone::new(Position::new(3, 10), Position::new(5, 12)));
+
+  spawn_bot(&mut commands, &archetypes, "worker", 15, 5);
+}
+
+pub fn spawn_resource(commands: &mut Commands, archetypes: &Archetypes, name: &str, x: u32, y: u32) {
+  let Some(definition) = archetypes.resource(name) else {
+    warn!("No resource archetype named {:?}", name);
+    return;
+  };
+
+  let mut entity = commands.spawn((
+    resource_renderable(definition),
+    Position::new(x, y),
+    Scrap::new(definition),
+    Archetype(name.to_string()),
+  ));
+
+  if definition.impassable {
+    entity.insert(Impassable {});
+  }
 }
 
-fn spawn_scrap(commands: &mut Commands, x: u32, y: u32) {
+pub fn spawn_spawner(commands: &mut Commands, archetypes: &Archetypes, name: &str, x: u32, y: u32) {
+  let Some(definition) = archetypes.spawner(name) else {
+    warn!("No spawner archetype named {:?}", name);
+    return;
+  };
+
   commands.spawn((
-    Renderable::new(0.2, 0.5, 0.5),
+    spawner_renderable(definition),
     Position::new(x, y),
-    Scrap::new(50),
-    Impassable {},
+    ResourceSpawner::new(definition.clone()),
+    Archetype(name.to_string()),
   ));
 }
 
-fn spawn_bot(commands: &mut Commands, x: u32, y: u32) {
+pub fn spawn_bot(commands: &mut Commands, archetypes: &Archetypes, name: &str, x: u32, y: u32) {
+  let Some(definition) = archetypes.bot(name) else {
+    warn!("No bot archetype named {:?}", name);
+    return;
+  };
+
+  let modules = Modules::default();
+  let stats = BotStats::new(definition, &modules, archetypes);
+
   commands.spawn((
-    Renderable::new(0.2, 0.2, 0.8),
+    bot_renderable(definition),
     Position::new(x, y),
-    Bot::new(),
+    Bot::new(stats.work_rate),
+    Cargo(Inventory::new(stats.carry_capacity)),
+    MovementSpeed::new(stats.ticks_per_tile, stats.speed_bonus),
+    Energy::full(stats.energy_capacity),
+    Health::new(definition.health),
+    modules,
+    Group::default(),
+    Archetype(name.to_string()),
+  ));
+}
+
+pub fn spawn_building(commands: &mut Commands, archetypes: &Archetypes, name: &str, x: u32, y: u32) -> Option<Entity> {
+  let Some(definition) = archetypes.building(name) else {
+    warn!("No building archetype named {:?}", name);
+    return None;
+  };
+
+  let mut entity = commands.spawn((
+    building_renderable(definition),
+    Position::new(x, y),
+    Building,
+    Health::new(definition.health),
+    Archetype(name.to_string()),
+  ));
+
+  if definition.impassable {
+    entity.insert(Impassable {});
+  }
+
+  if let Some(capacity) = definition.storage {
+    entity.insert(Storage(Inventory::new(capacity)));
+  }
+
+  if !definition.recipes.is_empty() {
+    entity.insert((
+      Fabricator::new(definition.recipes.clone()),
+      InputBuffer(Inventory::new(definition.buffer_capacity)),
+      OutputBuffer(Inventory::new(definition.buffer_capacity)),
+    ));
+  }
+
+  if definition.workshop {
+    entity.insert(Workshop);
+  }
+
+  if let Some(research) = definition.research {
+    entity.insert(Lab(research));
+  }
+
+  if let Some(door) = definition.door {
+    entity.insert(Door::new(door.open_ticks));
+  }
+
+  if let Some(turret) = definition.turret {
+    entity.insert(Turret::new(turret));
+  }
+
+  if let Some(power) = definition.power {
+    entity.insert(PowerNode::new(power));
+  }
+
+  if let Some(charger) = definition.charger {
+    entity.insert(Charger(charger));
+  }
+
+  if let Some(conveyor) = definition.conveyor {
+    entity.insert((Conveyor(conveyor), Heading::default()));
+  }
+
+  if let Some(inserter) = definition.inserter {
+    entity.insert((Inserter(inserter), Heading::default()));
+  }
+
+  if let Some(light) = definition.light {
+    entity.insert(Lamp(light));
+  }
+
+  if definition.bot_factory {
+    entity.insert((
+      BotFactory::default(),
+      InputBuffer(Inventory::new(definition.buffer_capacity)),
+    ));
+  }
+
+  Some(entity.id())
+}
+
+/// Lays down a site where bots bring the cost of building `name` and then
+/// build it, facing `heading` if it is a belt or inserter
+pub fn spawn_blueprint(commands: &mut Commands, archetypes: &Archetypes, name: &str, position: Position, heading: Heading) {
+  let Some(definition) = archetypes.building(name) else {
+    warn!("No building archetype named {:?}", name);
+    return;
+  };
+
+  // Faded and on the floor until it is built
+  let (r, g, b) = definition.color;
+  let mut entity = commands.spawn((
+    Renderable::new(r * 0.5, g * 0.5, b * 0.5).on_floor(0.8),
+    position,
+    Blueprint(name.to_string()),
+    InputBuffer(Inventory::new(definition.cost.values().sum())),
   ));
-}
\ No newline at end of file
+
+  if definition.conveyor.is_some() || definition.inserter.is_some() {
+    entity.insert(heading);
+  }
+}
+
+fn resource_renderable(definition: &ResourceDefinition) -> Renderable {
+  let (r, g, b) = definition.color;
+  let renderable = Renderable::new(r, g, b);
+  match definition.sprite {
+    Some(sprite) => renderable.with_animations(sprite.animations()),
+    None => renderable,
+  }
+}
+
+fn spawner_renderable(definition: &SpawnerDefinition) -> Renderable {
+  let (r, g, b) = definition.color;
+  let renderable = Renderable::new(r, g, b).on_floor(0.9);
+  match definition.sprite {
+    Some(sprite) => renderable.with_animations(sprite.animations()),
+    None => renderable,
+  }
+}
+
+fn building_renderable(definition: &BuildingDefinition) -> Renderable {
+  let (r, g, b) = definition.color;
+  let renderable = Renderable::new(r, g, b);
+  match definition.sprite {
+    Some(sprite) => renderable.with_animations(sprite.animations()),
+    None => renderable,
+  }
+}
+
+fn bot_renderable(definition: &BotDefinition) -> Renderable {
+  let (r, g, b) = definition.color;
+  let renderable = Renderable::new(r, g, b).with_rotation();
+  match definition.sprite {
+    Some(sprite) => renderable.with_animations(sprite.animations()),
+    None => renderable,
+  }
+}
+
+/// Everything a bot definition sets on a spawned bot
+type BotParts = (
+  &'static Archetype,
+  &'static Modules,
+  &'static mut Bot,
+  &'static mut MovementSpeed,
+  &'static mut Cargo,
+  &'static mut Energy,
+  &'static mut Health,
+  &'static mut Renderable,
+);
+
+/// Everything a building definition sets on a spawned building
+type BuildingParts = (
+  &'static Archetype,
+  &'static mut Renderable,
+  &'static mut Health,
+  Option<&'static mut Storage>,
+  Option<&'static mut Fabricator>,
+  Option<&'static mut Lab>,
+  Option<&'static mut Door>,
+  Option<&'static mut Turret>,
+  Option<&'static mut PowerNode>,
+  Option<&'static mut Charger>,
+  Option<&'static mut Conveyor>,
+  Option<&'static mut Inserter>,
+  Option<&'static mut Lamp>,
+);
+
+type OnlyBuildings = (With<Building>, Without<Bot>, Without<Scrap>);
+type OnlySpawners = (Without<Building>, Without<Bot>, Without<Scrap>);
+
+/// Re-applies definitions to already spawned entities when the archetype file
+/// is hot-reloaded.
+pub fn apply_archetype_changes(
+  mut commands: Commands,
+  archetypes: Res<Archetypes>,
+  mut resources: Query<(Entity, &Archetype, &mut Scrap, &mut Renderable), Without<Bot>>,
+  mut bots: Query<BotParts, Without<Scrap>>,
+  mut spawners: Query<(&Archetype, &mut ResourceSpawner, &mut Renderable), OnlySpawners>,
+  mut buildings: Query<BuildingParts, OnlyBuildings>,
+) {
+  if !archetypes.is_changed() || archetypes.is_added() {
+    return;
+  }
+
+  for (entity, archetype, mut scrap, mut renderable) in resources.iter_mut() {
+    let Some(definition) = archetypes.resource(&archetype.0) else {
+      continue;
+    };
+
+    scrap.redefine(definition);
+    renderable.restyle(resource_renderable(definition));
+
+    if definition.impassable {
+      commands.entity(entity).insert(Impassable {});
+    } else {
+      commands.entity(entity).remove::<Impassable>();
+    }
+  }
+
+  for (archetype, modules, mut bot, mut speed, mut cargo, mut energy, mut health, mut renderable) in bots.iter_mut() {
+    let Some(definition) = archetypes.bot(&archetype.0) else {
+      continue;
+    };
+
+    BotStats::new(definition, modules, &archetypes).apply(&mut bot, &mut speed, &mut cargo, &mut energy);
+    health.set_max(definition.health);
+    renderable.restyle(bot_renderable(definition));
+  }
+
+  for (archetype, mut renderable, mut health, storage, fabricator, lab, door, turret, power, charger, conveyor, inserter, lamp) in buildings.iter_mut() {
+    let Some(definition) = archetypes.building(&archetype.0) else {
+      continue;
+    };
+
+    renderable.restyle(building_renderable(definition));
+    health.set_max(definition.health);
+    if let (Some(mut storage), Some(capacity)) = (storage, definition.storage) {
+      storage.0.capacity = capacity;
+    }
+    if let Some(mut fabricator) = fabricator {
+      fabricator.recipes = definition.recipes.clone();
+    }
+    if let (Some(mut lab), Some(research)) = (lab, definition.research) {
+      lab.0 = research;
+    }
+    if let (Some(mut door), Some(definition)) = (door, definition.door) {
+      door.open_ticks = definition.open_ticks;
+      door.progress = door.progress.min(door.open_ticks);
+    }
+    if let (Some(mut turret), Some(definition)) = (turret, definition.turret) {
+      turret.definition = definition;
+    }
+    if let (Some(mut power), Some(definition)) = (power, definition.power) {
+      power.output = definition.output;
+      power.draw = definition.draw;
+      power.solar = definition.solar;
+    }
+    if let (Some(mut charger), Some(definition)) = (charger, definition.charger) {
+      charger.0 = definition;
+    }
+    if let (Some(mut conveyor), Some(definition)) = (conveyor, definition.conveyor) {
+      conveyor.0 = definition;
+    }
+    if let (Some(mut inserter), Some(definition)) = (inserter, definition.inserter) {
+      inserter.0 = definition;
+    }
+    if let (Some(mut lamp), Some(definition)) = (lamp, definition.light) {
+      lamp.0 = definition;
+    }
+  }
+
+  for (archetype, mut spawner, mut renderable) in spawners.iter_mut() {
+    let Some(definition) = archetypes.spawner(&archetype.0) else {
+      continue;
+    };
+
+    spawner.definition = definition.clone();
+    renderable.restyle(spawner_renderable(definition));
+  }
+}
@@ -0,0 +1,77 @@
+use bevy::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+use crate::grid::{Obstacles, Position};
+use crate::archetype::{Archetype, Archetypes, SpawnerDefinition};
+use crate::entities::scrap::Scrap;
+use crate::reservation::{ReservationSystem, ReservationKey};
+use crate::spawn::spawn_resource;
+use crate::simulation::SimulationRng;
+
+/// A junk pile or drop zone that puts new resource nodes down nearby
+#[derive(Component, Debug)]
+pub struct ResourceSpawner {
+  pub definition: SpawnerDefinition,
+  ticks: u32,
+}
+
+impl ResourceSpawner {
+  pub fn new(definition: SpawnerDefinition) -> Self {
+    Self { definition, ticks: 0 }
+  }
+
+  fn in_range(&self, center: &Position, position: &Position) -> bool {
+    center.x.abs_diff(position.x) <= self.definition.radius && center.y.abs_diff(position.y) <= self.definition.radius
+  }
+}
+
+/// Every interval, each spawner below its cap puts a node on a free tile in
+/// range. Nodes never land where anything stands, and impassable ones never
+/// land where they would wall off part of the map.
+pub fn run_spawners(
+  mut commands: Commands,
+  mut spawners: Query<(&mut ResourceSpawner, &Position)>,
+  nodes: Query<(&Position, &Archetype), With<Scrap>>,
+  obstacles: Obstacles,
+  archetypes: Res<Archetypes>,
+  reservations: Res<ReservationSystem>,
+  mut rng: ResMut<SimulationRng>,
+) {
+  let impassable_set = obstacles.impassable();
+  let grid = &obstacles.grid;
+  // Nodes spawned this tick only reach the grid next frame
+  let mut taken: HashSet<Position> = HashSet::new();
+
+  for (mut spawner, center) in spawners.iter_mut() {
+    spawner.ticks += 1;
+    if spawner.ticks < spawner.definition.interval_ticks {
+      continue;
+    }
+    spawner.ticks = 0;
+
+    let resource = spawner.definition.resource.clone();
+    let Some(definition) = archetypes.resource(&resource) else {
+      continue;
+    };
+    let existing = nodes.iter()
+      .filter(|(position, archetype)| archetype.0 == resource && spawner.in_range(center, position))
+      .count() as u32;
+    if existing >= spawner.definition.cap {
+      continue;
+    }
+
+    let candidates: Vec<Position> = grid.tiles.iter().flatten()
+      .filter(|tile| tile.residents.is_empty() && spawner.in_range(center, &tile.position))
+      .map(|tile| tile.position)
+      .filter(|position| !taken.contains(position) && !reservations.is_reserved(&ReservationKey::Tile(*position)))
+      .filter(|position| !definition.impassable || !grid.would_split(position, &impassable_set, &taken))
+      .collect();
+    if candidates.is_empty() {
+      continue;
+    }
+
+    let position = candidates[rng.0.gen_range(0..candidates.len())];
+    spawn_resource(&mut commands, &archetypes, &resource, position.x, position.y);
+    taken.insert(position);
+  }
+}
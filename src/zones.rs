@@ -0,0 +1,177 @@
+use bevy::prelude::*;
+use std::collections::{BTreeSet, HashSet};
+use crate::archetype::Archetypes;
+use crate::grid::{Grid, Position, Cursor};
+use crate::reservation::{ReservationSystem, ReservationKey};
+
+/// A player-designated rectangle of tiles where bots store loose items
+#[derive(Component, Debug)]
+pub struct StockpileZone {
+  pub min: Position,
+  pub max: Position,
+  /// Only this item is stored here, or anything when `None`
+  pub filter: Option<String>,
+}
+
+impl StockpileZone {
+  pub fn new(a: Position, b: Position) -> Self {
+    Self {
+      min: Position::new(a.x.min(b.x), a.y.min(b.y)),
+      max: Position::new(a.x.max(b.x), a.y.max(b.y)),
+      filter: None,
+    }
+  }
+
+  pub fn contains(&self, position: &Position) -> bool {
+    (self.min.x..=self.max.x).contains(&position.x) && (self.min.y..=self.max.y).contains(&position.y)
+  }
+
+  pub fn accepts(&self, item: &str) -> bool {
+    self.filter.as_deref().is_none_or(|filter| filter == item)
+  }
+}
+
+/// Whether an item on `position` already sits in a zone that accepts it
+pub fn is_stored(position: &Position, item: &str, zones: &Query<&StockpileZone>) -> bool {
+  zones.iter().any(|zone| zone.contains(position) && zone.accepts(item))
+}
+
+/// The zone tile accepting `item` that is the fewest steps from `start`, with
+/// room on its stack and no bot already bringing some. Also gives that room,
+/// as measured by `room`.
+pub fn nearest_free_zone_tile(
+  start: &Position,
+  item: &str,
+  grid: &Grid,
+  impassable: &HashSet<Entity>,
+  zones: &Query<&StockpileZone>,
+  reservations: &ReservationSystem,
+  room: impl Fn(&Position) -> u32,
+) -> Option<(Position, u32)> {
+  if !zones.iter().any(|zone| zone.accepts(item)) {
+    return None;
+  }
+
+  let tile = grid.nearest_tile(start, impassable, |position| {
+    is_stored(position, item, zones)
+      && !reservations.is_reserved(&ReservationKey::Tile(*position))
+      && room(position) > 0
+  })?;
+  Some((tile, room(&tile)))
+}
+
+/// Zone tool state: where the current drag started
+#[derive(Resource, Default)]
+pub struct ZoneTool {
+  drag_start: Option<Position>,
+}
+
+/// Item names a zone filter can be set to
+fn filterable_items(archetypes: &Archetypes) -> Vec<String> {
+  let library = &archetypes.library;
+  let items: BTreeSet<String> = library.resources.keys().cloned()
+    .chain(library.recipes.values().flat_map(|recipe| recipe.outputs.keys().cloned()))
+    .collect();
+  items.into_iter().collect()
+}
+
+/// Shift-drag with the left mouse button to mark a zone, right click a zone to
+/// remove it, and press F over a zone to cycle its item filter.
+pub fn edit_zones(
+  mut commands: Commands,
+  mut tool: ResMut<ZoneTool>,
+  archetypes: Res<Archetypes>,
+  mouse: Res<ButtonInput<MouseButton>>,
+  keys: Res<ButtonInput<KeyCode>>,
+  cursor: Cursor,
+  mut zones: Query<(Entity, &mut StockpileZone)>,
+) {
+  // Panels and buttons take the clicks over them, and a drag let go over one is dropped
+  if cursor.over_ui() {
+    if mouse.just_released(MouseButton::Left) {
+      tool.drag_start = None;
+    }
+    return;
+  }
+  let Some(tile) = cursor.tile() else {
+    return;
+  };
+
+  let shift = keys.pressed(KeyCode::ShiftLeft) || keys.pressed(KeyCode::ShiftRight);
+  if shift && mouse.just_pressed(MouseButton::Left) {
+    tool.drag_start = Some(tile);
+  }
+  if mouse.just_released(MouseButton::Left) {
+    if let Some(start) = tool.drag_start.take() {
+      commands.spawn(StockpileZone::new(start, tile));
+    }
+  }
+
+  if mouse.just_pressed(MouseButton::Right) {
+    for (entity, zone) in zones.iter() {
+      if zone.contains(&tile) {
+        commands.entity(entity).despawn();
+      }
+    }
+  }
+
+  if keys.just_pressed(KeyCode::KeyF) {
+    let items = filterable_items(&archetypes);
+    for (_, mut zone) in zones.iter_mut().filter(|(_, zone)| zone.contains(&tile)) {
+      // Cycles anything -> each item in turn -> anything
+      zone.filter = match &zone.filter {
+        None => items.first().cloned(),
+        Some(current) => items.iter()
+          .skip_while(|item| *item != current)
+          .nth(1)
+          .cloned(),
+      };
+    }
+  }
+}
+
+pub fn draw_zones(
+  grid: Res<Grid>,
+  tool: Res<ZoneTool>,
+  archetypes: Res<Archetypes>,
+  cursor: Cursor,
+  zones: Query<&StockpileZone>,
+  mut gizmos: Gizmos,
+) {
+  let draw = |gizmos: &mut Gizmos, zone: &StockpileZone, color: Color| {
+    let min = grid.world_position(&zone.min);
+    let max = grid.world_position(&zone.max);
+    let size = max - min + Vec2::splat(grid.tile_size);
+    gizmos.rect_2d((min + max) / 2.0, 0.0, size, color);
+  };
+
+  for zone in zones.iter() {
+    let color = match zone.filter.as_deref().and_then(|item| archetypes.resource(item)) {
+      Some(definition) => Color::srgb(definition.color.0, definition.color.1, definition.color.2),
+      None if zone.filter.is_some() => Color::srgb(0.9, 0.9, 0.4),
+      None => Color::srgb(0.4, 0.9, 0.4),
+    };
+    draw(&mut gizmos, zone, color);
+  }
+
+  if let (Some(start), Some(tile)) = (tool.drag_start, cursor.tile()) {
+    draw(&mut gizmos, &StockpileZone::new(start, tile), Color::srgba(1.0, 1.0, 1.0, 0.6));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zones_take_any_item_until_filtered() {
+    let mut zone = StockpileZone::new(Position::new(5, 6), Position::new(3, 2));
+    assert!(zone.accepts("scrap"));
+    assert!(zone.contains(&Position::new(4, 4)));
+    assert!(!zone.contains(&Position::new(6, 4)));
+
+    zone.filter = Some("plate".to_string());
+    assert!(zone.accepts("plate"));
+    assert!(!zone.accepts("scrap"));
+  }
+}